@@ -3,7 +3,7 @@ use crate::prelude::*;
 use bevy::prelude::*;
 
 // Level check and adjustment
-fn validate_level(level: u32) -> usize {
+pub(crate) fn validate_level(level: u32) -> usize {
     let idx = (level as usize).saturating_sub(1);
     idx.min(9) // Max level is 10 (index 9)
 }
@@ -15,13 +15,12 @@ pub fn town_hall(
     x: usize,
     y: usize,
 ) -> Entity {
-    let idx = validate_level(level);
-    let health = TOWNHALL_HEALTH[idx];
+    let stats = level_stats(BuildingType::TownHall, level);
 
     commands
         .spawn((
             TownHall,
-            Building::new_with_level(level, health),
+            Building::new_with_level(level, stats.health),
             GridPosition::new_full(x, y, TOWNHALL_SIZE.x as usize, TOWNHALL_SIZE.y as usize),
             assets.get(&BuildingType::TownHall).to_component(),
         ))
@@ -35,14 +34,13 @@ pub fn gold_collector(
     x: usize,
     y: usize,
 ) -> Entity {
-    let idx = validate_level(level);
-    let health = COLLECTOR_HEALTH[idx];
-    let rate = COLLECTOR_RATE[idx];
+    let stats = level_stats(BuildingType::Collector(ResourceType::Gold), level);
 
     commands
         .spawn((
-            ResourceCollector::new(ResourceType::Gold, rate),
-            Building::new_with_level(level, health),
+            ResourceCollector::new(ResourceType::Gold, stats.rate, stats.capacity as f32),
+            BuildingType::Collector(ResourceType::Gold),
+            Building::new_with_level(level, stats.health),
             GridPosition::new_full(x, y, COLLECTOR_SIZE.x as usize, COLLECTOR_SIZE.y as usize),
             assets
                 .get(&BuildingType::Collector(ResourceType::Gold))
@@ -58,14 +56,13 @@ pub fn elixir_collector(
     x: usize,
     y: usize,
 ) -> Entity {
-    let idx = validate_level(level);
-    let health = COLLECTOR_HEALTH[idx];
-    let rate = COLLECTOR_RATE[idx];
+    let stats = level_stats(BuildingType::Collector(ResourceType::Elixir), level);
 
     commands
         .spawn((
-            ResourceCollector::new(ResourceType::Elixir, rate),
-            Building::new_with_level(level, health),
+            ResourceCollector::new(ResourceType::Elixir, stats.rate, stats.capacity as f32),
+            BuildingType::Collector(ResourceType::Elixir),
+            Building::new_with_level(level, stats.health),
             GridPosition::new_full(x, y, COLLECTOR_SIZE.x as usize, COLLECTOR_SIZE.y as usize),
             assets
                 .get(&BuildingType::Collector(ResourceType::Elixir))
@@ -81,14 +78,13 @@ pub fn gold_storage(
     x: usize,
     y: usize,
 ) -> Entity {
-    let idx = validate_level(level);
-    let health = STORAGE_HEALTH[idx];
-    let capacity = STORAGE_CAPACITY[idx];
+    let stats = level_stats(BuildingType::Storage(ResourceType::Gold), level);
 
     commands
         .spawn((
-            Storage::new(ResourceType::Gold, capacity),
-            Building::new_with_level(level, health),
+            Storage::new(ResourceType::Gold, stats.capacity),
+            BuildingType::Storage(ResourceType::Gold),
+            Building::new_with_level(level, stats.health),
             GridPosition::new_full(x, y, STORAGE_SIZE.x as usize, STORAGE_SIZE.y as usize),
             assets
                 .get(&BuildingType::Storage(ResourceType::Gold))
@@ -104,14 +100,13 @@ pub fn elixir_storage(
     x: usize,
     y: usize,
 ) -> Entity {
-    let idx = validate_level(level);
-    let health = STORAGE_HEALTH[idx];
-    let capacity = STORAGE_CAPACITY[idx];
+    let stats = level_stats(BuildingType::Storage(ResourceType::Elixir), level);
 
     commands
         .spawn((
-            Storage::new(ResourceType::Elixir, capacity),
-            Building::new_with_level(level, health),
+            Storage::new(ResourceType::Elixir, stats.capacity),
+            BuildingType::Storage(ResourceType::Elixir),
+            Building::new_with_level(level, stats.health),
             GridPosition::new_full(x, y, STORAGE_SIZE.x as usize, STORAGE_SIZE.y as usize),
             assets
                 .get(&BuildingType::Storage(ResourceType::Elixir))
@@ -127,18 +122,101 @@ pub fn defense_tower(
     x: usize,
     y: usize,
 ) -> Entity {
-    let idx = validate_level(level);
-    let health = DEFENSE_HEALTH[idx];
-    let damage = DEFENSE_DAMAGE[idx];
-    let range = DEFENSE_RANGE[idx];
-    let speed = DEFENSE_SPEED[idx];
+    let stats = level_stats(BuildingType::Defense(DefenseKind::Tower), level);
 
     commands
         .spawn((
-            Defense::new(damage, range, speed),
-            Building::new_with_level(level, health),
+            Defense::new(stats.damage, stats.range, stats.attack_speed),
+            BuildingType::Defense(DefenseKind::Tower),
+            Building::new_with_level(level, stats.health),
             GridPosition::new_full(x, y, DEFENSE_SIZE.x as usize, DEFENSE_SIZE.y as usize),
-            assets.get(&BuildingType::Defense).to_component(),
+            assets
+                .get(&BuildingType::Defense(DefenseKind::Tower))
+                .to_component(),
+        ))
+        .id()
+}
+
+pub fn mortar(
+    commands: &mut Commands,
+    assets: &BuildingAssets,
+    level: u32,
+    x: usize,
+    y: usize,
+) -> Entity {
+    let stats = level_stats(BuildingType::Defense(DefenseKind::Mortar), level);
+
+    commands
+        .spawn((
+            Defense::new_with_targeting(
+                stats.damage,
+                stats.range,
+                stats.attack_speed,
+                DefenseTargeting::Ground,
+            )
+            .with_min_range(MORTAR_MIN_RANGE),
+            SplashDamage::new(MORTAR_SPLASH_RADIUS),
+            BuildingType::Defense(DefenseKind::Mortar),
+            Building::new_with_level(level, stats.health),
+            GridPosition::new_full(x, y, MORTAR_SIZE.x as usize, MORTAR_SIZE.y as usize),
+            assets
+                .get(&BuildingType::Defense(DefenseKind::Mortar))
+                .to_component(),
+        ))
+        .id()
+}
+
+pub fn air_defense(
+    commands: &mut Commands,
+    assets: &BuildingAssets,
+    level: u32,
+    x: usize,
+    y: usize,
+) -> Entity {
+    let stats = level_stats(BuildingType::Defense(DefenseKind::AirDefense), level);
+
+    commands
+        .spawn((
+            Defense::new_with_targeting(
+                stats.damage,
+                stats.range,
+                stats.attack_speed,
+                DefenseTargeting::Air,
+            ),
+            BuildingType::Defense(DefenseKind::AirDefense),
+            Building::new_with_level(level, stats.health),
+            GridPosition::new_full(
+                x,
+                y,
+                AIR_DEFENSE_SIZE.x as usize,
+                AIR_DEFENSE_SIZE.y as usize,
+            ),
+            assets
+                .get(&BuildingType::Defense(DefenseKind::AirDefense))
+                .to_component(),
+        ))
+        .id()
+}
+
+pub fn inferno_tower(
+    commands: &mut Commands,
+    assets: &BuildingAssets,
+    level: u32,
+    x: usize,
+    y: usize,
+) -> Entity {
+    let stats = level_stats(BuildingType::Defense(DefenseKind::Inferno), level);
+
+    commands
+        .spawn((
+            Defense::new(stats.damage, stats.range, stats.attack_speed),
+            BeamAttack::new(INFERNO_RAMP_RATE, INFERNO_MAX_MULTIPLIER),
+            BuildingType::Defense(DefenseKind::Inferno),
+            Building::new_with_level(level, stats.health),
+            GridPosition::new_full(x, y, INFERNO_SIZE.x as usize, INFERNO_SIZE.y as usize),
+            assets
+                .get(&BuildingType::Defense(DefenseKind::Inferno))
+                .to_component(),
         ))
         .id()
 }
@@ -150,19 +228,189 @@ pub fn wall(
     x: usize,
     y: usize,
 ) -> Entity {
-    let idx = validate_level(level);
-    let health = WALL_HEALTH[idx];
+    let stats = level_stats(BuildingType::Wall, level);
 
     commands
         .spawn((
-            Building::new_with_level(level, health),
-            Wall::new(health),
+            Building::new_with_level(level, stats.health),
+            Wall::default(),
             GridPosition::new_full(x, y, WALL_SIZE.x as usize, WALL_SIZE.y as usize),
             assets.get(&BuildingType::Wall).to_component(),
         ))
         .id()
 }
 
+pub fn bomb(
+    commands: &mut Commands,
+    assets: &BuildingAssets,
+    level: u32,
+    x: usize,
+    y: usize,
+) -> Entity {
+    let stats = level_stats(BuildingType::Trap(TrapKind::Bomb), level);
+
+    commands
+        .spawn((
+            Trap::new(
+                TrapKind::Bomb,
+                stats.damage,
+                BOMB_TRIGGER_RADIUS,
+                DefenseTargeting::Ground,
+                BOMB_REARM_COST,
+            )
+            .with_splash_radius(BOMB_SPLASH_RADIUS),
+            BuildingType::Trap(TrapKind::Bomb),
+            GridPosition::new_full(x, y, TRAP_SIZE.x as usize, TRAP_SIZE.y as usize),
+            assets
+                .get(&BuildingType::Trap(TrapKind::Bomb))
+                .to_component(),
+        ))
+        .id()
+}
+
+pub fn spring_trap(
+    commands: &mut Commands,
+    assets: &BuildingAssets,
+    level: u32,
+    x: usize,
+    y: usize,
+) -> Entity {
+    let stats = level_stats(BuildingType::Trap(TrapKind::SpringTrap), level);
+
+    commands
+        .spawn((
+            Trap::new(
+                TrapKind::SpringTrap,
+                stats.damage,
+                SPRING_TRAP_TRIGGER_RADIUS,
+                DefenseTargeting::Ground,
+                SPRING_TRAP_REARM_COST,
+            ),
+            BuildingType::Trap(TrapKind::SpringTrap),
+            GridPosition::new_full(x, y, TRAP_SIZE.x as usize, TRAP_SIZE.y as usize),
+            assets
+                .get(&BuildingType::Trap(TrapKind::SpringTrap))
+                .to_component(),
+        ))
+        .id()
+}
+
+pub fn air_mine(
+    commands: &mut Commands,
+    assets: &BuildingAssets,
+    level: u32,
+    x: usize,
+    y: usize,
+) -> Entity {
+    let stats = level_stats(BuildingType::Trap(TrapKind::AirMine), level);
+
+    commands
+        .spawn((
+            Trap::new(
+                TrapKind::AirMine,
+                stats.damage,
+                AIR_MINE_TRIGGER_RADIUS,
+                DefenseTargeting::Air,
+                AIR_MINE_REARM_COST,
+            ),
+            BuildingType::Trap(TrapKind::AirMine),
+            GridPosition::new_full(x, y, TRAP_SIZE.x as usize, TRAP_SIZE.y as usize),
+            assets
+                .get(&BuildingType::Trap(TrapKind::AirMine))
+                .to_component(),
+        ))
+        .id()
+}
+
+pub fn spell_factory(
+    commands: &mut Commands,
+    assets: &BuildingAssets,
+    level: u32,
+    x: usize,
+    y: usize,
+) -> Entity {
+    let stats = level_stats(BuildingType::SpellFactory, level);
+
+    commands
+        .spawn((
+            SpellFactory::new(stats.capacity),
+            Building::new_with_level(level, stats.health),
+            GridPosition::new_full(
+                x,
+                y,
+                SPELL_FACTORY_SIZE.x as usize,
+                SPELL_FACTORY_SIZE.y as usize,
+            ),
+            assets.get(&BuildingType::SpellFactory).to_component(),
+        ))
+        .id()
+}
+
+pub fn king_altar(
+    commands: &mut Commands,
+    assets: &BuildingAssets,
+    level: u32,
+    x: usize,
+    y: usize,
+) -> Entity {
+    hero_altar(commands, assets, HeroType::King, level, x, y)
+}
+
+pub fn queen_altar(
+    commands: &mut Commands,
+    assets: &BuildingAssets,
+    level: u32,
+    x: usize,
+    y: usize,
+) -> Entity {
+    hero_altar(commands, assets, HeroType::Queen, level, x, y)
+}
+
+fn hero_altar(
+    commands: &mut Commands,
+    assets: &BuildingAssets,
+    hero: HeroType,
+    level: u32,
+    x: usize,
+    y: usize,
+) -> Entity {
+    let stats = level_stats(BuildingType::HeroAltar(hero), level);
+
+    commands
+        .spawn((
+            HeroAltar::new(hero),
+            BuildingType::HeroAltar(hero),
+            Building::new_with_level(level, stats.health),
+            GridPosition::new_full(x, y, HERO_ALTAR_SIZE.x as usize, HERO_ALTAR_SIZE.y as usize),
+            assets.get(&BuildingType::HeroAltar(hero)).to_component(),
+        ))
+        .id()
+}
+
+pub fn clan_castle(
+    commands: &mut Commands,
+    assets: &BuildingAssets,
+    level: u32,
+    x: usize,
+    y: usize,
+) -> Entity {
+    let stats = level_stats(BuildingType::ClanCastle, level);
+
+    commands
+        .spawn((
+            ClanCastle::new(stats.capacity),
+            Building::new_with_level(level, stats.health),
+            GridPosition::new_full(
+                x,
+                y,
+                CLAN_CASTLE_SIZE.x as usize,
+                CLAN_CASTLE_SIZE.y as usize,
+            ),
+            assets.get(&BuildingType::ClanCastle).to_component(),
+        ))
+        .id()
+}
+
 // Function for custom positioning of any building
 pub fn place_building(
     commands: &mut Commands,
@@ -173,7 +421,12 @@ pub fn place_building(
     y: usize,
 ) -> Entity {
     match building_type {
-        BuildingType::Defense => defense_tower(commands, assets, level, x, y),
+        BuildingType::Defense(kind) => match kind {
+            DefenseKind::Tower => defense_tower(commands, assets, level, x, y),
+            DefenseKind::Mortar => mortar(commands, assets, level, x, y),
+            DefenseKind::AirDefense => air_defense(commands, assets, level, x, y),
+            DefenseKind::Inferno => inferno_tower(commands, assets, level, x, y),
+        },
         BuildingType::Collector(resource) => match resource {
             ResourceType::Gold => gold_collector(commands, assets, level, x, y),
             ResourceType::Elixir => elixir_collector(commands, assets, level, x, y),
@@ -184,15 +437,223 @@ pub fn place_building(
         },
         BuildingType::Wall => wall(commands, assets, level, x, y),
         BuildingType::TownHall => town_hall(commands, assets, level, x, y),
+        BuildingType::Trap(kind) => match kind {
+            TrapKind::Bomb => bomb(commands, assets, level, x, y),
+            TrapKind::SpringTrap => spring_trap(commands, assets, level, x, y),
+            TrapKind::AirMine => air_mine(commands, assets, level, x, y),
+        },
+        BuildingType::SpellFactory => spell_factory(commands, assets, level, x, y),
+        BuildingType::HeroAltar(hero) => match hero {
+            HeroType::King => king_altar(commands, assets, level, x, y),
+            HeroType::Queen => queen_altar(commands, assets, level, x, y),
+        },
+        BuildingType::ClanCastle => clan_castle(commands, assets, level, x, y),
     }
 }
 
 pub fn to_size(building_type: BuildingType) -> (usize, usize) {
     match building_type {
-        BuildingType::Defense => (DEFENSE_SIZE.x as usize, DEFENSE_SIZE.y as usize),
+        BuildingType::Defense(kind) => match kind {
+            DefenseKind::Tower => (DEFENSE_SIZE.x as usize, DEFENSE_SIZE.y as usize),
+            DefenseKind::Mortar => (MORTAR_SIZE.x as usize, MORTAR_SIZE.y as usize),
+            DefenseKind::AirDefense => (AIR_DEFENSE_SIZE.x as usize, AIR_DEFENSE_SIZE.y as usize),
+            DefenseKind::Inferno => (INFERNO_SIZE.x as usize, INFERNO_SIZE.y as usize),
+        },
         BuildingType::Collector(_) => (COLLECTOR_SIZE.x as usize, COLLECTOR_SIZE.y as usize),
         BuildingType::Storage(_) => (STORAGE_SIZE.x as usize, STORAGE_SIZE.y as usize),
         BuildingType::Wall => (WALL_SIZE.x as usize, WALL_SIZE.y as usize),
         BuildingType::TownHall => (TOWNHALL_SIZE.x as usize, TOWNHALL_SIZE.y as usize),
+        BuildingType::Trap(_) => (TRAP_SIZE.x as usize, TRAP_SIZE.y as usize),
+        BuildingType::SpellFactory => {
+            (SPELL_FACTORY_SIZE.x as usize, SPELL_FACTORY_SIZE.y as usize)
+        }
+        BuildingType::HeroAltar(_) => (HERO_ALTAR_SIZE.x as usize, HERO_ALTAR_SIZE.y as usize),
+        BuildingType::ClanCastle => (CLAN_CASTLE_SIZE.x as usize, CLAN_CASTLE_SIZE.y as usize),
     }
 }
+
+/// What a building's level decides, see [`level_stats`]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LevelStats {
+    pub health: f32,
+    /// Per hit for defenses and traps, per second for beams
+    pub damage: f32,
+    pub range: f32,
+    /// Hits per second
+    pub attack_speed: f32,
+    /// Resources a collector makes per second
+    pub rate: f32,
+    /// What a collector, storage, spell factory or clan castle holds
+    pub capacity: u32,
+}
+
+/// Stats of a building at `level`, read when it is placed and when it is
+/// upgraded
+pub fn level_stats(building_type: BuildingType, level: u32) -> LevelStats {
+    let idx = validate_level(level);
+    match building_type {
+        BuildingType::TownHall => LevelStats {
+            health: TOWNHALL_HEALTH[idx],
+            ..default()
+        },
+        BuildingType::Collector(_) => LevelStats {
+            health: COLLECTOR_HEALTH[idx],
+            rate: COLLECTOR_RATE[idx],
+            capacity: COLLECTOR_CAPACITY[idx] as u32,
+            ..default()
+        },
+        BuildingType::Storage(_) => LevelStats {
+            health: STORAGE_HEALTH[idx],
+            capacity: STORAGE_CAPACITY[idx],
+            ..default()
+        },
+        BuildingType::Defense(DefenseKind::Tower) => LevelStats {
+            health: DEFENSE_HEALTH[idx],
+            damage: DEFENSE_DAMAGE[idx],
+            range: DEFENSE_RANGE[idx],
+            attack_speed: DEFENSE_SPEED[idx],
+            ..default()
+        },
+        BuildingType::Defense(DefenseKind::Mortar) => LevelStats {
+            health: MORTAR_HEALTH[idx],
+            damage: MORTAR_DAMAGE[idx],
+            range: MORTAR_RANGE[idx],
+            attack_speed: MORTAR_SPEED[idx],
+            ..default()
+        },
+        BuildingType::Defense(DefenseKind::AirDefense) => LevelStats {
+            health: AIR_DEFENSE_HEALTH[idx],
+            damage: AIR_DEFENSE_DAMAGE[idx],
+            range: AIR_DEFENSE_RANGE[idx],
+            attack_speed: AIR_DEFENSE_SPEED[idx],
+            ..default()
+        },
+        // Beams don't use attack speed, damage is applied every frame
+        BuildingType::Defense(DefenseKind::Inferno) => LevelStats {
+            health: INFERNO_HEALTH[idx],
+            damage: INFERNO_DAMAGE[idx],
+            range: INFERNO_RANGE[idx],
+            attack_speed: 1.0,
+            ..default()
+        },
+        BuildingType::Wall => LevelStats {
+            health: WALL_HEALTH[idx],
+            ..default()
+        },
+        BuildingType::Trap(TrapKind::Bomb) => LevelStats {
+            damage: BOMB_DAMAGE[idx],
+            ..default()
+        },
+        // Springs remove the unit no matter its health
+        BuildingType::Trap(TrapKind::SpringTrap) => LevelStats {
+            damage: f32::INFINITY,
+            ..default()
+        },
+        BuildingType::Trap(TrapKind::AirMine) => LevelStats {
+            damage: AIR_MINE_DAMAGE[idx],
+            ..default()
+        },
+        BuildingType::SpellFactory => LevelStats {
+            health: SPELL_FACTORY_HEALTH[idx],
+            capacity: SPELL_FACTORY_CAPACITY[idx],
+            ..default()
+        },
+        BuildingType::HeroAltar(_) => LevelStats {
+            health: HERO_ALTAR_HEALTH[idx],
+            ..default()
+        },
+        BuildingType::ClanCastle => LevelStats {
+            health: CLAN_CASTLE_HEALTH[idx],
+            capacity: CLAN_CASTLE_CAPACITY[idx],
+            ..default()
+        },
+    }
+}
+
+/// Max and min attack range of a defense at this level
+pub fn defense_range(kind: DefenseKind, level: u32) -> (f32, f32) {
+    let range = level_stats(BuildingType::Defense(kind), level).range;
+    match kind {
+        DefenseKind::Mortar => (range, MORTAR_MIN_RANGE),
+        _ => (range, 0.0),
+    }
+}
+
+/// Moves a placed building to `(x, y)`, keeping the map in sync. `false` when
+/// the spot is taken, the building then stays where it was
+pub fn move_building(
+    map: &mut TileMap,
+    entity: Entity,
+    building_type: BuildingType,
+    grid: &mut GridPosition,
+    transform: &mut Transform,
+    x: usize,
+    y: usize,
+) -> bool {
+    let size = to_size(building_type);
+    map.remove_entity(grid.x, grid.y, entity, size);
+    let moved = map.can_place(x, y, size);
+    if moved {
+        transform.translation.x += x as f32 - grid.x as f32;
+        transform.translation.y += y as f32 - grid.y as f32;
+        grid.x = x;
+        grid.y = y;
+    }
+    map.place(grid.x, grid.y, entity, size);
+    moved
+}
+
+/// Takes a placed building off the map and despawns it
+pub fn remove_building(
+    commands: &mut Commands,
+    map: &mut TileMap,
+    entity: Entity,
+    building_type: BuildingType,
+    position: &GridPosition,
+) {
+    map.remove_entity(position.x, position.y, entity, to_size(building_type));
+    commands.entity(entity).despawn_recursive();
+}
+
+/// Raises the building a level in place: it keeps its entity, so stored
+/// resources, brewing spells, donated troops and the selection stay. `false`
+/// at max level
+pub fn upgrade_building(
+    commands: &mut Commands,
+    entity: Entity,
+    building_type: BuildingType,
+    level: u32,
+) -> bool {
+    if level as usize >= BUILDING_UPGRADE_COST.len() {
+        return false;
+    }
+
+    let stats = level_stats(building_type, level + 1);
+    commands.queue(move |world: &mut World| {
+        let Ok(mut entity) = world.get_entity_mut(entity) else {
+            return;
+        };
+        if let Some(mut building) = entity.get_mut::<Building>() {
+            *building = Building::new_with_level(level + 1, stats.health);
+        }
+        if let Some(mut defense) = entity.get_mut::<Defense>() {
+            defense.attack_damage = stats.damage;
+            defense.attack_range = stats.range;
+            defense.attack_speed = stats.attack_speed;
+        }
+        if let Some(mut collector) = entity.get_mut::<ResourceCollector>() {
+            collector.production_rate = stats.rate;
+            collector.capacity = stats.capacity as f32;
+        }
+        if let Some(mut storage) = entity.get_mut::<Storage>() {
+            storage.capacity = stats.capacity;
+        }
+        if let Some(mut factory) = entity.get_mut::<SpellFactory>() {
+            factory.capacity = stats.capacity;
+        }
+        if let Some(mut castle) = entity.get_mut::<ClanCastle>() {
+            castle.capacity = stats.capacity;
+        }
+    });
+    true
+}
@@ -1,100 +1,426 @@
 use crate::prelude::*;
 use bevy::{
-    input::{mouse::MouseWheel, touch::TouchPhase},
+    math::{Affine3A, Vec3A},
     prelude::*,
 };
 
 pub fn setup_camera(mut commands: Commands) {
-    let game_camera = GameCamera::default();
+    let game_camera = GameCamera {
+        target: Vec2::new(50.0, 45.0),
+        ..default()
+    };
     let mut orthographic_projection = OrthographicProjection::default_2d();
     orthographic_projection.scale = 1.0 / game_camera.zoom;
     commands.spawn((
-        Transform::from_xyz(50.0, 45.0, 0.0),
+        Transform::from_translation(game_camera.target.extend(0.0)),
         orthographic_projection,
         Camera2d,
         game_camera,
     ));
 }
 
+/// Moves the camera and its target together, for input that has to follow
+/// the pointer without easing
+fn pan_now(transform: &mut Transform, controller: &mut GameCamera, delta: Vec2) {
+    transform.translation += delta.extend(0.0);
+    controller.target += delta;
+    controller.zoom_anchor = None;
+}
+
+/// Offset of a screen position from the window center, y pointing up
+fn screen_offset(window: &Window, position: Vec2) -> Vec2 {
+    let offset = position - window.size() / 2.0;
+    Vec2::new(offset.x, -offset.y)
+}
+
+/// Half size of the grid area a view of `half_size` covers, as a bounding
+/// box in the isometric view
+pub fn view_half_extent(view_mode: ViewMode, half_size: Vec2) -> Vec2 {
+    let right = view_mode.unproject(Vec2::new(half_size.x, 0.0)).abs();
+    let up = view_mode.unproject(Vec2::new(0.0, half_size.y)).abs();
+    right + up
+}
+
+/// Screen position to world position, the camera moves in screen space so
+/// this is still projected by the [`ViewMode`]
+fn screen_to_world(window: &Window, transform: &Transform, scale: f32, position: Vec2) -> Vec2 {
+    transform.translation.truncate() + screen_offset(window, position) * scale
+}
+
+/// Drags the map with the pointer, the grabbed world point stays under it.
+/// A press released before moving `drag_threshold` is sent as a [`WorldClick`],
+/// touch drags are left to [`camera_touch`]
 pub fn camera_movement(
-    mut camera_query: Query<(&mut Transform, &mut GameCamera)>,
-    mouse: Res<ButtonInput<MouseButton>>,
-    mut touch_input: EventReader<TouchInput>,
-    windows: Query<&Window>,
-    time: Res<Time>,
+    mut camera_query: Query<(&Camera, &GlobalTransform, &mut Transform, &mut GameCamera)>,
+    actions: Res<ActionState>,
+    view_mode: Res<ViewMode>,
+    ui_query: Query<&Interaction>,
+    mut clicks: EventWriter<WorldClick>,
 ) {
-    let window = windows.single();
-    let (mut transform, mut controller) = camera_query.single_mut();
+    let (camera, global_transform, mut transform, mut controller) = camera_query.single_mut();
+    let cursor = actions.pointer;
+    let world =
+        cursor.and_then(|cursor| camera.viewport_to_world_2d(global_transform, cursor).ok());
 
-    // Handle mouse input
-    if mouse.just_pressed(MouseButton::Left) {
-        controller.is_dragging = true;
-        controller.last_position = window.cursor_position().map(|pos| Vec2::new(pos.x, pos.y));
+    // Presses on buttons or the minimap belong to the UI
+    let over_ui = ui_query
+        .iter()
+        .any(|interaction| *interaction != Interaction::None);
+    if actions.just_pressed(InputAction::Place) && !over_ui {
+        controller.press_position = cursor;
+        // Measured against the target, which a drag past the bounds keeps
+        // following while the camera itself is held back
+        controller.grab =
+            world.map(|world| world - transform.translation.truncate() + controller.target);
     }
 
-    if mouse.just_released(MouseButton::Left) {
-        controller.is_dragging = false;
-        controller.last_position = None;
+    if let (Some(start), Some(cursor)) = (controller.press_position, cursor) {
+        if start.distance(cursor) > controller.drag_threshold {
+            controller.is_dragging = true;
+        }
+    }
+
+    if controller.is_dragging && actions.pressed(InputAction::Place) && !actions.pointer_is_touch {
+        if let (Some(grab), Some(world)) = (controller.grab, world) {
+            let pointed = world - transform.translation.truncate() + controller.target;
+            pan_now(&mut transform, &mut controller, grab - pointed);
+        }
     }
 
-    // Continue drag if pressed
-    if controller.is_dragging {
-        if let Some(current_pos) = window.cursor_position().map(|pos| Vec2::new(pos.x, pos.y)) {
-            if let Some(last_pos) = controller.last_position {
-                let delta = current_pos - last_pos;
-                transform.translation.x -= delta.x * controller.speed * time.delta_secs();
-                transform.translation.y += delta.y * controller.speed * time.delta_secs();
+    if actions.just_released(InputAction::Place) {
+        if !controller.is_dragging && controller.press_position.is_some() {
+            if let Some(world) = world {
+                let position = view_mode.unproject(world);
+                clicks.send(WorldClick { position });
             }
-            controller.last_position = Some(current_pos);
         }
+        controller.is_dragging = false;
+        controller.press_position = None;
+        controller.grab = None;
     }
+}
+
+/// One finger pans and flings the camera, two fingers pinch zoom around
+/// their midpoint
+pub fn camera_touch(
+    mut camera_query: Query<(&mut Transform, &mut OrthographicProjection, &mut GameCamera)>,
+    touches: Res<Touches>,
+    windows: Query<&Window>,
+    time: Res<Time>,
+) {
+    let window = windows.single();
+    let (mut transform, mut projection, mut controller) = camera_query.single_mut();
+    let delta_secs = time.delta_secs();
 
-    // Handle touch input
-    let touches = touch_input.read().collect::<Vec<_>>();
-    match touches.len() {
-        1 => {
-            if let Some(touch) = touch_input.read().next() {
-                match touch.phase {
-                    TouchPhase::Started => {
-                        controller.is_dragging = true;
-                        controller.last_position = Some(touch.position);
-                    }
-                    TouchPhase::Ended | TouchPhase::Canceled => {
-                        controller.is_dragging = false;
-                        controller.last_position = None;
-                    }
-                    TouchPhase::Moved if controller.is_dragging => {
-                        if let Some(last_pos) = controller.last_position {
-                            let delta = touch.position - last_pos;
-                            transform.translation.x -=
-                                delta.x * controller.speed * time.delta_secs();
-                            transform.translation.y +=
-                                delta.y * controller.speed * time.delta_secs();
-                        }
-                        controller.last_position = Some(touch.position);
-                    }
-                    _ => (),
+    let pressed = touches.iter().collect::<Vec<_>>();
+    match pressed.as_slice() {
+        [touch] => {
+            let delta = touch.delta() * projection.scale;
+            let world_delta = Vec2::new(-delta.x, delta.y);
+            pan_now(&mut transform, &mut controller, world_delta);
+            if delta_secs > 0.0 {
+                controller.velocity = world_delta / delta_secs;
+            }
+        }
+        [first, second, ..] => {
+            controller.velocity = Vec2::ZERO;
+            let previous_distance = first
+                .previous_position()
+                .distance(second.previous_position());
+            let distance = first.position().distance(second.position());
+            let previous_midpoint = (first.previous_position() + second.previous_position()) / 2.0;
+            let midpoint = (first.position() + second.position()) / 2.0;
+
+            // The world point under the fingers stays under them
+            let anchor = screen_to_world(window, &transform, projection.scale, previous_midpoint);
+            if previous_distance > 0.0 {
+                controller.zoom = (controller.zoom * distance / previous_distance)
+                    .clamp(controller.min_zoom, controller.max_zoom);
+                controller.target_zoom = controller.zoom;
+                projection.scale = 1.0 / controller.zoom;
+            }
+            let moved = screen_to_world(window, &transform, projection.scale, midpoint);
+            pan_now(&mut transform, &mut controller, anchor - moved);
+        }
+        [] => {
+            if controller.velocity != Vec2::ZERO {
+                let delta = controller.velocity * delta_secs;
+                pan_now(&mut transform, &mut controller, delta);
+                let decay = (-controller.friction * delta_secs).exp();
+                controller.velocity *= decay;
+                if controller.velocity.length() < 0.1 {
+                    controller.velocity = Vec2::ZERO;
                 }
             }
         }
-        2 => warn!("two Touches, should zoom"),
-        _ => (),
     }
 }
 
+/// Zooms toward the cursor, the world point under it stays in place. The
+/// zoom keys zoom around the center of the screen
 pub fn camera_zoom(
-    mut scroll_event: EventReader<MouseWheel>,
-    mut camera_query: Query<(&mut OrthographicProjection, &mut GameCamera), With<Camera2d>>,
+    actions: Res<ActionState>,
+    mut camera_query: Query<(&Transform, &OrthographicProjection, &mut GameCamera), With<Camera2d>>,
+    windows: Query<&Window>,
+    time: Res<Time>,
 ) {
-    let scroll = scroll_event.read().map(|e| e.y).sum::<f32>();
+    let held = actions.zoom_hold * KEY_ZOOM_STEPS * time.delta_secs();
+    let scroll = actions.zoom_steps + held;
     if scroll == 0.0 {
         return;
     }
+    let window = windows.single();
+
+    for (transform, projection, mut camera) in camera_query.iter_mut() {
+        camera.target_zoom += scroll * camera.zoom_speed;
+        camera.target_zoom = camera.target_zoom.clamp(camera.min_zoom, camera.max_zoom);
+
+        let cursor = window
+            .cursor_position()
+            .filter(|_| actions.zoom_steps != 0.0);
+        let Some(cursor) = cursor else {
+            camera.zoom_anchor = None;
+            continue;
+        };
+        let anchor = screen_to_world(window, transform, projection.scale, cursor);
+        let offset = screen_offset(window, cursor);
+        camera.zoom_anchor = Some((anchor, offset));
+        camera.target = anchor - offset / camera.target_zoom;
+    }
+}
+
+/// Pans with the pan actions, and when the cursor touches the border
+pub fn camera_pan_keys(
+    mut camera_query: Query<(&OrthographicProjection, &mut GameCamera)>,
+    actions: Res<ActionState>,
+    windows: Query<&Window>,
+    time: Res<Time>,
+) {
+    let window = windows.single();
+    let (projection, mut camera) = camera_query.single_mut();
+
+    let mut direction = Vec2::ZERO;
+    if camera.key_pan {
+        direction += actions.pan;
+    }
+    if camera.edge_pan {
+        if let Some(cursor) = window.cursor_position() {
+            let size = window.size();
+            let margin = camera.edge_margin;
+            if cursor.x <= margin {
+                direction.x -= 1.0;
+            } else if cursor.x >= size.x - margin {
+                direction.x += 1.0;
+            }
+            // Screen y points down
+            if cursor.y <= margin {
+                direction.y += 1.0;
+            } else if cursor.y >= size.y - margin {
+                direction.y -= 1.0;
+            }
+        }
+    }
+    if direction == Vec2::ZERO {
+        return;
+    }
 
-    for (mut projection, mut camera) in camera_query.iter_mut() {
-        camera.zoom += scroll * camera.zoom_speed;
-        camera.zoom = camera.zoom.clamp(camera.min_zoom, camera.max_zoom);
+    let delta =
+        direction.clamp_length_max(1.0) * camera.pan_speed * projection.scale * time.delta_secs();
+    camera.target += delta;
+    camera.zoom_anchor = None;
+}
 
+/// Eases the camera toward its pan and zoom targets
+pub fn ease_camera(
+    mut camera_query: Query<(&mut Transform, &mut OrthographicProjection, &mut GameCamera)>,
+    time: Res<Time>,
+) {
+    for (mut transform, mut projection, mut camera) in camera_query.iter_mut() {
+        let factor = camera.easing.factor(time.delta_secs());
+        camera.zoom += (camera.target_zoom - camera.zoom) * factor;
+        if (camera.target_zoom - camera.zoom).abs() < 0.001 {
+            camera.zoom = camera.target_zoom;
+        }
         projection.scale = 1.0 / camera.zoom;
+
+        let position = match camera.zoom_anchor {
+            Some((anchor, offset)) => anchor - offset * projection.scale,
+            None => transform.translation.truncate().lerp(camera.target, factor),
+        };
+        transform.translation = position.extend(transform.translation.z);
+
+        if camera.zoom == camera.target_zoom {
+            camera.zoom_anchor = None;
+        }
+    }
+}
+
+/// Area the camera center can be in, so the viewport stays over the map as
+/// it is drawn in the view mode
+fn camera_bounds(map: &TileMap, view_mode: ViewMode, half_extent: Vec2, padding: f32) -> Rect {
+    let size = Vec2::new(map.width as f32, map.height as f32);
+    let drawn = [Vec2::ZERO, Vec2::X * size.x, Vec2::Y * size.y, size]
+        .map(|corner| view_mode.project(corner));
+    let drawn_min = drawn.into_iter().reduce(Vec2::min).unwrap_or_default();
+    let drawn_max = drawn.into_iter().reduce(Vec2::max).unwrap_or_default();
+    let min = drawn_min + half_extent - padding;
+    let max = drawn_max - half_extent + padding;
+    // A viewport bigger than the map just stays centered on it
+    let center = (drawn_min + drawn_max) / 2.0;
+    Rect::from_corners(
+        Vec2::select(min.cmple(max), min, center),
+        Vec2::select(min.cmple(max), max, center),
+    )
+}
+
+/// Past the bounds the camera follows less and less, up to `limit` away
+fn rubber_band(value: f32, min: f32, max: f32, limit: f32) -> f32 {
+    let stretch = |overshoot: f32| limit * (1.0 - 1.0 / (overshoot / limit + 1.0));
+    if value < min {
+        min - stretch(min - value)
+    } else if value > max {
+        max + stretch(value - max)
+    } else {
+        value
+    }
+}
+
+/// Keeps the camera over the map, a drag past the edge stretches
+/// elastically and springs back on release
+pub fn clamp_camera(
+    mut camera_query: Query<(&mut Transform, &OrthographicProjection, &mut GameCamera)>,
+    map_query: Query<&TileMap>,
+    windows: Query<&Window>,
+    touches: Res<Touches>,
+    view_mode: Res<ViewMode>,
+) {
+    let Ok(map) = map_query.get_single() else {
+        return;
+    };
+    let window = windows.single();
+    let (mut transform, projection, mut camera) = camera_query.single_mut();
+
+    let half_extent = window.size() / 2.0 * projection.scale;
+    let bounds = camera_bounds(map, *view_mode, half_extent, camera.bounds_padding);
+
+    let dragging = camera.is_dragging || touches.iter().next().is_some();
+    if dragging {
+        let limit = camera.elastic_distance * projection.scale;
+        let target = camera.target;
+        transform.translation.x = rubber_band(target.x, bounds.min.x, bounds.max.x, limit);
+        transform.translation.y = rubber_band(target.y, bounds.min.y, bounds.max.y, limit);
+    } else {
+        // Easing brings the camera back from wherever the drag left it
+        camera.target = camera.target.clamp(bounds.min, bounds.max);
+    }
+}
+
+/// Switches between the top-down and isometric views, the camera stays
+/// over the same tile
+pub fn toggle_view_mode(
+    actions: Res<ActionState>,
+    mut view_mode: ResMut<ViewMode>,
+    mut camera_query: Query<(&mut Transform, &mut GameCamera)>,
+) {
+    if !actions.just_pressed(InputAction::Rotate) {
+        return;
+    }
+    let previous = *view_mode;
+    *view_mode = view_mode.toggled();
+    let reproject = |point: Vec2| view_mode.project(previous.unproject(point));
+    for (mut transform, mut camera) in camera_query.iter_mut() {
+        let center = reproject(transform.translation.truncate());
+        transform.translation = center.extend(transform.translation.z);
+        camera.target = reproject(camera.target);
+        camera.zoom_anchor = None;
+    }
+    info!("View mode: {:?}", *view_mode);
+}
+
+/// Sorts the buildings by depth when the view changes or a building gets
+/// placed
+pub fn apply_view_mode(
+    view_mode: Res<ViewMode>,
+    mut building_query: Query<(Ref<GridPosition>, &GridSize, &mut Transform)>,
+) {
+    for (position, size, mut transform) in building_query.iter_mut() {
+        if view_mode.is_changed() || position.is_changed() {
+            transform.translation.z = view_mode.building_z(&position, size);
+        }
+    }
+}
+
+type ProjectedRoot = (Without<Parent>, Without<Node>, Without<Camera>);
+type ProjectedPart<'a> = (
+    &'a Transform,
+    &'a mut GlobalTransform,
+    Option<&'a Children>,
+    Has<GroundLayer>,
+    Has<GridPosition>,
+    Has<Mesh2d>,
+);
+
+/// Draws the world in the [`ViewMode`], gameplay keeps working on the grid.
+///
+/// Runs after transform propagation and rebuilds the global transforms from
+/// the local ones, so it never projects twice. Everything keeps standing
+/// upright and only moves, apart from the [`GroundLayer`] and buildings
+/// drawn as placeholder footprints, which lie flat and turn into diamonds
+pub fn project_view(
+    view_mode: Res<ViewMode>,
+    roots: Query<Entity, ProjectedRoot>,
+    mut parts: Query<ProjectedPart>,
+) {
+    // Propagation already left everything top-down
+    if *view_mode == ViewMode::TopDown && !view_mode.is_changed() {
+        return;
+    }
+    let matrix = view_mode.matrix();
+    let ground = Affine3A::from_mat3(Mat3::from_mat2(matrix));
+
+    let mut stack: Vec<(Entity, Affine3A, Vec2)> = Vec::new();
+    for root in roots.iter() {
+        let Ok((transform, ..)) = parts.get(root) else {
+            continue;
+        };
+        let origin = transform.translation.truncate();
+        let shift = view_mode.project(origin) - origin;
+        stack.push((root, Affine3A::IDENTITY, shift));
     }
+
+    while let Some((entity, parent, shift)) = stack.pop() {
+        let Ok((transform, mut global, children, flat, footprint, mesh)) = parts.get_mut(entity)
+        else {
+            continue;
+        };
+        let affine = parent * transform.compute_affine();
+        *global = if flat || (footprint && mesh) {
+            GlobalTransform::from(ground * affine)
+        } else {
+            let mut moved = affine;
+            moved.translation += Vec3A::from(shift.extend(0.0));
+            GlobalTransform::from(moved)
+        };
+        for child in children.into_iter().flatten() {
+            stack.push((*child, affine, shift));
+        }
+    }
+}
+
+/// Outline of a footprint on the ground, a diamond in the isometric view
+pub fn draw_footprint(
+    gizmos: &mut Gizmos,
+    view_mode: ViewMode,
+    min: Vec2,
+    size: Vec2,
+    color: impl Into<Color>,
+) {
+    let corners = [
+        min,
+        min + Vec2::X * size.x,
+        min + size,
+        min + Vec2::Y * size.y,
+        min,
+    ];
+    gizmos.linestrip_2d(corners.map(|corner| view_mode.project(corner)), color);
 }
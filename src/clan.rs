@@ -0,0 +1,75 @@
+//! Clan castle reinforcements, donated by the local [`Clan`]
+use crate::prelude::*;
+use bevy::prelude::*;
+
+/// Fills the castles one donation at a time while a request is open
+pub fn receive_donations(
+    time: Res<Time>,
+    mut clan: ResMut<Clan>,
+    mut query: Query<&mut ClanCastle, Without<Destroyed>>,
+) {
+    if !clan.requested {
+        return;
+    }
+    clan.donation_timer -= time.delta_secs();
+    if clan.donation_timer > 0.0 {
+        return;
+    }
+    clan.donation_timer = DONATION_INTERVAL;
+
+    let Some(mut castle) = query.iter_mut().find(|castle| !castle.is_full()) else {
+        clan.requested = false;
+        return;
+    };
+    let Some(troop) = clan.donate() else {
+        clan.requested = false;
+        return;
+    };
+    castle.troops.push(troop);
+    info!("{} donated a {}", clan.name, troop.name());
+}
+
+/// Castle troops come out as defenders once a raider gets close, they are
+/// used up by the fight. Only in a raid, when the player attacks the castle
+/// holds their reinforcements instead, see [`take_reinforcements`]
+pub fn release_clan_troops(
+    mut commands: Commands,
+    assets: Res<UnitAssets>,
+    mut castle_query: Query<(&Transform, &mut ClanCastle), Without<Destroyed>>,
+    attacker_query: Query<(&Transform, &Unit), Without<Defender>>,
+) {
+    for (transform, mut castle) in castle_query.iter_mut() {
+        if castle.troops.is_empty() {
+            continue;
+        }
+
+        let post = transform.translation.truncate();
+        let triggered = attacker_query.iter().any(|(attacker, unit)| {
+            unit.health > 0.0
+                && post.distance(attacker.translation.truncate()) <= castle.trigger_radius
+        });
+        if !triggered {
+            continue;
+        }
+
+        let guard_radius = castle.trigger_radius;
+        for troop in castle.troops.drain(..) {
+            let entity = deploy_unit(&mut commands, &assets, troop, 1, post);
+            commands
+                .entity(entity)
+                .insert(Defender::new(post, guard_radius));
+        }
+        info!("Clan castle troops are defending");
+    }
+}
+
+/// Empties the first castle with troops, for deploying them in an attack
+pub fn take_reinforcements(
+    castles: &mut Query<&mut ClanCastle, Without<Destroyed>>,
+) -> Vec<UnitType> {
+    castles
+        .iter_mut()
+        .find(|castle| !castle.troops.is_empty())
+        .map(|mut castle| castle.troops.drain(..).collect())
+        .unwrap_or_default()
+}
@@ -0,0 +1,486 @@
+//! Battle logic: defenses shooting units and units attacking buildings
+use crate::prelude::*;
+use bevy::{prelude::*, utils::HashSet};
+
+pub fn acquire_defense_targets(
+    mut defense_query: Query<(&Transform, &mut Defense), Without<Destroyed>>,
+    unit_query: Query<(Entity, &Transform, &Unit), Without<Defender>>,
+) {
+    for (defense_transform, mut defense) in defense_query.iter_mut() {
+        let origin = defense_transform.translation.truncate();
+
+        // Keep shooting the current target while it stays valid
+        if let Some(target) = defense.target {
+            if let Ok((_, transform, unit)) = unit_query.get(target) {
+                let distance = origin.distance(transform.translation.truncate());
+                if defense.can_target(unit, distance) {
+                    continue;
+                }
+            }
+        }
+
+        defense.target = unit_query
+            .iter()
+            .filter_map(|(entity, transform, unit)| {
+                let distance = origin.distance(transform.translation.truncate());
+                defense
+                    .can_target(unit, distance)
+                    .then_some((entity, distance))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(entity, _)| entity);
+    }
+}
+
+type FiringDefense<'a> = (&'a Transform, &'a mut Defense, Option<&'a SplashDamage>);
+/// Destroyed and frozen defenses hold fire
+type ActiveDefense = (Without<Destroyed>, Without<Frozen>);
+
+pub fn fire_defenses(
+    mut commands: Commands,
+    time: Res<Time>,
+    assets: Res<ProjectileAssets>,
+    mut defense_query: Query<FiringDefense, (Without<BeamAttack>, ActiveDefense)>,
+    unit_query: Query<&Transform, With<Unit>>,
+) {
+    for (transform, mut defense, splash) in defense_query.iter_mut() {
+        defense.cooldown = (defense.cooldown - time.delta_secs()).max(0.0);
+
+        let Some(target) = defense.target else {
+            continue;
+        };
+        if defense.cooldown > 0.0 {
+            continue;
+        }
+
+        let projectile = match splash {
+            // Shells land where the target was when fired
+            Some(splash) => {
+                let Ok(target_transform) = unit_query.get(target) else {
+                    continue;
+                };
+                Projectile::new_area(
+                    target_transform.translation.truncate(),
+                    splash.radius,
+                    defense.targeting,
+                    defense.attack_damage,
+                )
+            }
+            None => Projectile::new(target, defense.attack_damage),
+        };
+
+        defense.cooldown = defense.attack_interval();
+        commands.spawn((
+            projectile,
+            Transform::from_translation(transform.translation.truncate().extend(PROJECTILE_Z)),
+            assets.to_component(),
+        ));
+    }
+}
+
+pub fn fire_beams(
+    time: Res<Time>,
+    mut gizmos: Gizmos,
+    view_mode: Res<ViewMode>,
+    mut defense_query: Query<(&Transform, &Defense, &mut BeamAttack), ActiveDefense>,
+    mut unit_query: Query<(&Transform, &mut Unit)>,
+) {
+    for (transform, defense, mut beam) in defense_query.iter_mut() {
+        // Switching targets resets the ramp up
+        if beam.locked_on != defense.target {
+            beam.locked_on = defense.target;
+            beam.multiplier = 1.0;
+        }
+
+        let Some((target_transform, mut unit)) = defense
+            .target
+            .and_then(|target| unit_query.get_mut(target).ok())
+        else {
+            continue;
+        };
+
+        unit.health -= defense.attack_damage * beam.multiplier * time.delta_secs();
+        beam.multiplier =
+            (beam.multiplier + beam.ramp_rate * time.delta_secs()).min(beam.max_multiplier);
+
+        gizmos.line_2d(
+            view_mode.project(transform.translation.truncate()),
+            view_mode.project(target_transform.translation.truncate()),
+            BEAM_COLOR,
+        );
+    }
+}
+
+pub fn move_projectiles(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut projectile_query: Query<(Entity, &mut Transform, &Projectile), Without<Unit>>,
+    mut unit_query: Query<(&Transform, &mut Unit), Without<Defender>>,
+) {
+    for (entity, mut transform, projectile) in projectile_query.iter_mut() {
+        let destination = match projectile.target {
+            ProjectileTarget::Unit(target) => match unit_query.get(target) {
+                Ok((target_transform, _)) => target_transform.translation.truncate(),
+                // The target died or left the battle before the hit
+                Err(_) => {
+                    commands.entity(entity).despawn_recursive();
+                    continue;
+                }
+            },
+            ProjectileTarget::Area { position, .. } => position,
+        };
+
+        let to_target = destination - transform.translation.truncate();
+        let step = projectile.speed * time.delta_secs();
+        if to_target.length() > step {
+            transform.translation += (to_target.normalize() * step).extend(0.0);
+            continue;
+        }
+
+        match projectile.target {
+            ProjectileTarget::Unit(target) => {
+                if let Ok((_, mut unit)) = unit_query.get_mut(target) {
+                    unit.health -= projectile.damage;
+                }
+            }
+            ProjectileTarget::Area {
+                position,
+                radius,
+                targeting,
+            } => {
+                for (unit_transform, mut unit) in unit_query.iter_mut() {
+                    let distance = position.distance(unit_transform.translation.truncate());
+                    if distance <= radius && targeting.allows(unit.layer) {
+                        unit.health -= projectile.damage;
+                    }
+                }
+            }
+        }
+        trace!("Projectile {entity:?} hit {:?}", projectile.target);
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+pub fn despawn_dead_units(mut commands: Commands, query: Query<(Entity, &Unit)>) {
+    for (entity, unit) in query.iter() {
+        if unit.health <= 0.0 {
+            debug!("Unit {entity:?} died");
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// Distance from `point` to the closest edge of a building, 0 when inside
+pub fn distance_to_building(point: Vec2, transform: &Transform, size: &GridSize) -> f32 {
+    let half_size = Vec2::new(size.width as f32, size.height as f32) / 2.0;
+    let offset = (point - transform.translation.truncate()).abs() - half_size;
+    offset.max(Vec2::ZERO).length()
+}
+
+type AttackableBuilding<'a> = (Entity, &'a Transform, &'a GridSize, &'a BuildingType);
+type AttackableFilter = (With<Building>, Without<Destroyed>, Without<Unit>);
+
+pub fn update_unit_targets(
+    map_query: Query<&TileMap>,
+    mut unit_query: Query<(&Transform, &Unit, &mut UnitState), Without<Defender>>,
+    building_query: Query<AttackableBuilding, AttackableFilter>,
+    obstacle_query: Query<&BuildingType, Without<Destroyed>>,
+) {
+    let Ok(map) = map_query.get_single() else {
+        return;
+    };
+
+    for (transform, unit, mut state) in unit_query.iter_mut() {
+        let position = transform.translation.truncate();
+
+        if state
+            .target
+            .is_some_and(|target| !building_query.contains(target))
+        {
+            state.reset();
+        }
+
+        // Walls are only attacked when they are in the way
+        if state.target.is_none() {
+            state.target = building_query
+                .iter()
+                .filter(|(_, _, _, building_type)| **building_type != BuildingType::Wall)
+                .map(|(entity, building_transform, size, building_type)| {
+                    let rank = unit.target_preference.rank(building_type);
+                    let distance = distance_to_building(position, building_transform, size);
+                    (entity, rank, distance)
+                })
+                .min_by(|a, b| a.1.cmp(&b.1).then(a.2.total_cmp(&b.2)))
+                .map(|(entity, _, _)| entity);
+        }
+
+        let Some(target) = state.target else {
+            continue;
+        };
+        if unit.layer == UnitLayer::Air || !state.path.is_empty() || state.unreachable {
+            continue;
+        }
+        let Ok((_, target_transform, size, _)) = building_query.get(target) else {
+            continue;
+        };
+        if distance_to_building(position, target_transform, size) <= unit.attack_range {
+            continue;
+        }
+
+        let start = position.max(Vec2::ZERO).floor().as_uvec2();
+        match find_path(map, start, target, |entity| {
+            obstacle_query.get(entity).ok().and_then(passage_cost)
+        }) {
+            Some(path) => state.path = path,
+            None => state.unreachable = true,
+        }
+    }
+}
+
+type MovingUnit<'a> = (
+    &'a mut Transform,
+    &'a Unit,
+    &'a mut UnitState,
+    Option<&'a WallBreaker>,
+    Option<&'a Raged>,
+);
+type DamageableBuilding<'a> = (&'a Transform, &'a GridSize, &'a mut Building, Has<Wall>);
+
+pub fn move_units(
+    time: Res<Time>,
+    map_query: Query<&TileMap>,
+    mut unit_query: Query<MovingUnit, Without<Defender>>,
+    mut defender_query: Query<(&Transform, &mut Unit), With<Defender>>,
+    mut building_query: Query<DamageableBuilding, (Without<Destroyed>, Without<Unit>)>,
+) {
+    let Ok(map) = map_query.get_single() else {
+        return;
+    };
+
+    for (mut transform, unit, mut state, wall_breaker, raged) in unit_query.iter_mut() {
+        let (damage_multiplier, speed_multiplier) = raged
+            .map(|raged| (raged.damage_multiplier, raged.speed_multiplier))
+            .unwrap_or((1.0, 1.0));
+        state.cooldown = (state.cooldown - time.delta_secs()).max(0.0);
+        let position = transform.translation.truncate();
+
+        // Defenders that come close get fought back before anything else
+        if let Some((_, mut defender)) =
+            defender_query
+                .iter_mut()
+                .find(|(defender_transform, defender)| {
+                    defender.health > 0.0
+                        && position.distance(defender_transform.translation.truncate())
+                            <= unit.attack_range + UNIT_SIZE
+                })
+        {
+            if state.cooldown <= 0.0 {
+                defender.health -= unit.attack_damage * damage_multiplier;
+                state.cooldown = 1.0 / unit.attack_speed.max(f32::EPSILON);
+            }
+            continue;
+        }
+
+        let Some(target) = state.target else {
+            continue;
+        };
+
+        // A wall standing on the next step has to come down first
+        let blocker = state
+            .path
+            .first()
+            .and_then(|tile| map.get_entity_at(tile.x as usize, tile.y as usize))
+            .filter(|entity| {
+                *entity != target && building_query.get(*entity).is_ok_and(|(.., wall)| wall)
+            });
+
+        let Ok((building_transform, size, mut building, is_wall)) =
+            building_query.get_mut(blocker.unwrap_or(target))
+        else {
+            state.reset();
+            continue;
+        };
+
+        if distance_to_building(position, building_transform, size) <= unit.attack_range {
+            if state.cooldown <= 0.0 {
+                let multiplier = match wall_breaker {
+                    Some(wall_breaker) if is_wall => wall_breaker.damage_multiplier,
+                    _ => 1.0,
+                };
+                building.health -= unit.attack_damage * multiplier * damage_multiplier;
+                state.cooldown = 1.0 / unit.attack_speed.max(f32::EPSILON);
+            }
+            continue;
+        }
+
+        // Ground units follow their path, air units fly straight
+        let waypoint = match (unit.layer, state.path.first()) {
+            (UnitLayer::Ground, Some(tile)) => tile.as_vec2() + Vec2::splat(0.5),
+            _ => building_transform.translation.truncate(),
+        };
+        let to_waypoint = waypoint - position;
+        let step = unit.movement_speed * speed_multiplier * time.delta_secs();
+        if to_waypoint.length() <= step {
+            transform.translation = waypoint.extend(transform.translation.z);
+            if unit.layer == UnitLayer::Ground && !state.path.is_empty() {
+                state.path.remove(0);
+            }
+        } else {
+            transform.translation += (to_waypoint.normalize() * step).extend(0.0);
+        }
+    }
+}
+
+type DestroyableBuilding<'a> = (
+    Entity,
+    &'a Building,
+    &'a BuildingType,
+    &'a GridPosition,
+    &'a GridSize,
+);
+
+pub fn destroy_buildings(
+    mut commands: Commands,
+    mut map_query: Query<&mut TileMap>,
+    query: Query<DestroyableBuilding, Without<Destroyed>>,
+    mut events: EventWriter<BuildingDestroyed>,
+) {
+    let Ok(mut map) = map_query.get_single_mut() else {
+        return;
+    };
+
+    for (entity, building, building_type, position, size) in query.iter() {
+        if building.health > 0.0 {
+            continue;
+        }
+        debug!("{building_type:?} {entity:?} destroyed");
+        map.remove(position.x, position.y, (size.width, size.height));
+        commands
+            .entity(entity)
+            .insert((Destroyed, Visibility::Hidden));
+        events.send(BuildingDestroyed {
+            entity,
+            building_type: *building_type,
+        });
+    }
+}
+
+type DefendingUnit<'a> = (
+    &'a mut Transform,
+    &'a Unit,
+    &'a mut UnitState,
+    &'a Defender,
+    Option<&'a Raged>,
+);
+
+/// Defenders chase the closest attacker around their post, and walk back
+/// to it when there is none
+pub fn update_defenders(
+    time: Res<Time>,
+    mut defender_query: Query<DefendingUnit>,
+    mut attacker_query: Query<(Entity, &Transform, &mut Unit), Without<Defender>>,
+) {
+    for (mut transform, unit, mut state, defender, raged) in defender_query.iter_mut() {
+        state.cooldown = (state.cooldown - time.delta_secs()).max(0.0);
+        let damage_multiplier = raged.map_or(1.0, |raged| raged.damage_multiplier);
+        let speed_multiplier = raged.map_or(1.0, |raged| raged.speed_multiplier);
+        let position = transform.translation.truncate();
+
+        let target = attacker_query
+            .iter()
+            .filter(|(_, attacker_transform, attacker)| {
+                attacker.health > 0.0
+                    && defender
+                        .post
+                        .distance(attacker_transform.translation.truncate())
+                        <= defender.guard_radius
+            })
+            .map(|(entity, attacker_transform, _)| {
+                (entity, attacker_transform.translation.truncate())
+            })
+            .min_by(|a, b| position.distance(a.1).total_cmp(&position.distance(b.1)));
+
+        let destination = match target {
+            Some((entity, target_position)) => {
+                if position.distance(target_position) <= unit.attack_range + UNIT_SIZE {
+                    if state.cooldown <= 0.0 {
+                        if let Ok((_, _, mut attacker)) = attacker_query.get_mut(entity) {
+                            attacker.health -= unit.attack_damage * damage_multiplier;
+                        }
+                        state.cooldown = 1.0 / unit.attack_speed.max(f32::EPSILON);
+                    }
+                    continue;
+                }
+                target_position
+            }
+            None => defender.post,
+        };
+
+        let to_destination = destination - position;
+        let step = unit.movement_speed * speed_multiplier * time.delta_secs();
+        if to_destination.length() <= step {
+            transform.translation = destination.extend(transform.translation.z);
+        } else {
+            transform.translation += (to_destination.normalize() * step).extend(0.0);
+        }
+    }
+}
+
+/// Paths go stale when anything on the map falls. A fallen wall only
+/// re-routes the units walking through or along its segment, anything else
+/// re-routes every unit
+pub fn reroute_units(
+    mut events: EventReader<BuildingDestroyed>,
+    wall_query: Query<(Entity, &Wall, &GridPosition, Has<Destroyed>)>,
+    mut query: Query<&mut UnitState>,
+) {
+    let destroyed: Vec<Entity> = events.read().map(|event| event.entity).collect();
+    if destroyed.is_empty() {
+        return;
+    }
+
+    // Tiles next to the segments the fallen walls belonged to, None when
+    // something other than a wall fell
+    let fallen_segments: Option<Vec<usize>> = destroyed
+        .iter()
+        .map(|entity| {
+            wall_query
+                .get(*entity)
+                .ok()
+                .map(|(_, wall, ..)| wall.segment)
+        })
+        .collect();
+    let near_fallen_walls = fallen_segments.map(|segments| {
+        wall_query
+            .iter()
+            .filter(|(entity, wall, _, fallen)| {
+                segments.contains(&wall.segment) && (!fallen || destroyed.contains(entity))
+            })
+            .flat_map(|(_, _, position, _)| {
+                let (x, y) = (position.x as u32, position.y as u32);
+                [
+                    UVec2::new(x, y),
+                    UVec2::new(x + 1, y),
+                    UVec2::new(x.wrapping_sub(1), y),
+                    UVec2::new(x, y + 1),
+                    UVec2::new(x, y.wrapping_sub(1)),
+                ]
+            })
+            .collect::<HashSet<_>>()
+    });
+
+    for mut state in query.iter_mut() {
+        if state
+            .target
+            .is_some_and(|target| destroyed.contains(&target))
+        {
+            state.reset();
+        } else if state.unreachable
+            || near_fallen_walls
+                .as_ref()
+                .is_none_or(|tiles| state.path.iter().any(|tile| tiles.contains(tile)))
+        {
+            state.reroute();
+        }
+    }
+}
@@ -1,7 +1,16 @@
+use crate::prelude::*;
 use bevy::{prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+/// Everything belonging to a game session, despawned when going back to the
+/// main menu
+#[derive(Component, Debug, Clone, Copy, Default)]
+#[require(StateScoped<InGame>(|| StateScoped(InGame)))]
+pub struct GameWorldMarker;
 
 // Map and grid components
 #[derive(Component)]
+#[require(GameWorldMarker)]
 pub struct TileMap {
     pub width: usize,               // 100 as you mentioned
     pub height: usize,              // 100 as you mentioned
@@ -45,6 +54,29 @@ impl TileMap {
         true
     }
 
+    pub fn remove(&mut self, x: usize, y: usize, size: (usize, usize)) {
+        for dy in 0..size.1 {
+            for dx in 0..size.0 {
+                if let Some(idx) = self.get_tile_idx(x + dx, y + dy) {
+                    self.tiles[idx] = None;
+                }
+            }
+        }
+    }
+
+    /// Like [`TileMap::remove`], but only frees the tiles `entity` holds
+    pub fn remove_entity(&mut self, x: usize, y: usize, entity: Entity, size: (usize, usize)) {
+        for dy in 0..size.1 {
+            for dx in 0..size.0 {
+                if let Some(idx) = self.get_tile_idx(x + dx, y + dy) {
+                    if self.tiles[idx] == Some(entity) {
+                        self.tiles[idx] = None;
+                    }
+                }
+            }
+        }
+    }
+
     pub fn place(&mut self, x: usize, y: usize, entity: Entity, size: (usize, usize)) {
         if self.can_place(x, y, size) {
             for dy in 0..size.1 {
@@ -77,7 +109,7 @@ impl GridPosition {
             Transform::from_xyz(
                 x as f32 + width as f32 / 2.0,
                 y as f32 + height as f32 / 2.0,
-                1.0,
+                BUILDING_Z,
             ),
             GridSize { width, height },
         )
@@ -99,19 +131,111 @@ pub struct UnitPosition {
 }
 
 // Resource types
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ResourceType {
     Gold,
     Elixir,
 }
 
-#[derive(Component, Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[derive(Component, Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub enum BuildingType {
     TownHall,
     Collector(ResourceType),
     Storage(ResourceType),
-    Defense,
+    Defense(DefenseKind),
     Wall,
+    Trap(TrapKind),
+    SpellFactory,
+    HeroAltar(HeroType),
+    ClanCastle,
+}
+
+impl BuildingType {
+    /// Every building that can be placed, in palette order
+    pub const ALL: [BuildingType; 17] = [
+        BuildingType::TownHall,
+        BuildingType::Collector(ResourceType::Gold),
+        BuildingType::Collector(ResourceType::Elixir),
+        BuildingType::Storage(ResourceType::Gold),
+        BuildingType::Storage(ResourceType::Elixir),
+        BuildingType::Defense(DefenseKind::Tower),
+        BuildingType::Defense(DefenseKind::Mortar),
+        BuildingType::Defense(DefenseKind::AirDefense),
+        BuildingType::Defense(DefenseKind::Inferno),
+        BuildingType::Wall,
+        BuildingType::Trap(TrapKind::Bomb),
+        BuildingType::Trap(TrapKind::SpringTrap),
+        BuildingType::Trap(TrapKind::AirMine),
+        BuildingType::SpellFactory,
+        BuildingType::HeroAltar(HeroType::King),
+        BuildingType::HeroAltar(HeroType::Queen),
+        BuildingType::ClanCastle,
+    ];
+
+    /// Row of this building's sprites in the building atlas, the layout the
+    /// art is drawn in. Kept apart from [`BuildingType::ALL`] so reordering
+    /// the palette doesn't shuffle the art
+    pub fn atlas_row(&self) -> u32 {
+        match self {
+            BuildingType::TownHall => 0,
+            BuildingType::Collector(ResourceType::Gold) => 1,
+            BuildingType::Collector(ResourceType::Elixir) => 2,
+            BuildingType::Storage(ResourceType::Gold) => 3,
+            BuildingType::Storage(ResourceType::Elixir) => 4,
+            BuildingType::Defense(DefenseKind::Tower) => 5,
+            BuildingType::Defense(DefenseKind::Mortar) => 6,
+            BuildingType::Defense(DefenseKind::AirDefense) => 7,
+            BuildingType::Defense(DefenseKind::Inferno) => 8,
+            BuildingType::Wall => 9,
+            BuildingType::Trap(TrapKind::Bomb) => 10,
+            BuildingType::Trap(TrapKind::SpringTrap) => 11,
+            BuildingType::Trap(TrapKind::AirMine) => 12,
+            BuildingType::SpellFactory => 13,
+            BuildingType::HeroAltar(HeroType::King) => 14,
+            BuildingType::HeroAltar(HeroType::Queen) => 15,
+            BuildingType::ClanCastle => 16,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BuildingType::TownHall => "Town Hall",
+            BuildingType::Collector(ResourceType::Gold) => "Gold Collector",
+            BuildingType::Collector(ResourceType::Elixir) => "Elixir Collector",
+            BuildingType::Storage(ResourceType::Gold) => "Gold Storage",
+            BuildingType::Storage(ResourceType::Elixir) => "Elixir Storage",
+            BuildingType::Defense(kind) => kind.name(),
+            BuildingType::Wall => "Wall",
+            BuildingType::Trap(kind) => kind.name(),
+            BuildingType::SpellFactory => "Spell Factory",
+            BuildingType::HeroAltar(HeroType::King) => "King Altar",
+            BuildingType::HeroAltar(HeroType::Queen) => "Queen Altar",
+            BuildingType::ClanCastle => "Clan Castle",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DefenseKind {
+    /// Generic single-target tower, shoots ground and air
+    Tower,
+    /// Slow splash shells with a blind spot close to the building
+    Mortar,
+    /// Only shoots flying units
+    AirDefense,
+    /// Single-target beam whose damage ramps up while locked on
+    Inferno,
+}
+
+impl DefenseKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DefenseKind::Tower => "Tower",
+            DefenseKind::Mortar => "Mortar",
+            DefenseKind::AirDefense => "Air Defense",
+            DefenseKind::Inferno => "Inferno",
+        }
+    }
 }
 
 #[derive(Component, Debug, Clone)]
@@ -122,15 +246,27 @@ pub struct TownHall;
 pub struct ResourceCollector {
     pub resource_type: ResourceType,
     pub production_rate: f32,
+    /// Produced and waiting to be collected
+    pub stored: f32,
+    pub capacity: f32,
 }
 
 impl ResourceCollector {
-    pub fn new(resource_type: ResourceType, production_rate: f32) -> Self {
+    pub fn new(resource_type: ResourceType, production_rate: f32, capacity: f32) -> Self {
         ResourceCollector {
             resource_type,
             production_rate,
+            stored: 0.0,
+            capacity,
         }
     }
+
+    /// Empties the collector into the player resources
+    pub fn collect(&mut self, resources: &mut PlayerResources) -> f32 {
+        let amount = std::mem::take(&mut self.stored);
+        *resources.resources.entry(self.resource_type).or_insert(0.0) += amount as f64;
+        amount
+    }
 }
 
 #[derive(Component, Debug, Clone)]
@@ -148,37 +284,355 @@ impl Storage {
     }
 }
 
+// BuildingType depends on the kind, so constructors insert it themselves
 #[derive(Component, Debug, Clone)]
-#[require(BuildingType(|| BuildingType::Defense))]
 pub struct Defense {
     pub attack_damage: f32,
     pub attack_range: f32,
+    /// Units closer than this can't be hit
+    pub min_range: f32,
     pub attack_speed: f32,
+    pub targeting: DefenseTargeting,
+    pub target: Option<Entity>,
+    /// Seconds left until the next shot
+    pub cooldown: f32,
 }
 
 impl Defense {
     pub fn new(attack_damage: f32, attack_range: f32, attack_speed: f32) -> Self {
+        Defense::new_with_targeting(
+            attack_damage,
+            attack_range,
+            attack_speed,
+            DefenseTargeting::Both,
+        )
+    }
+
+    pub fn new_with_targeting(
+        attack_damage: f32,
+        attack_range: f32,
+        attack_speed: f32,
+        targeting: DefenseTargeting,
+    ) -> Self {
         Defense {
             attack_damage,
             attack_range,
+            min_range: 0.0,
             attack_speed,
+            targeting,
+            target: None,
+            cooldown: 0.0,
+        }
+    }
+
+    pub fn with_min_range(mut self, min_range: f32) -> Self {
+        self.min_range = min_range;
+        self
+    }
+
+    /// Seconds between two shots, attack_speed is in shots per second
+    pub fn attack_interval(&self) -> f32 {
+        1.0 / self.attack_speed.max(f32::EPSILON)
+    }
+
+    pub fn can_target(&self, unit: &Unit, distance: f32) -> bool {
+        unit.health > 0.0
+            && self.targeting.allows(unit.layer)
+            && (self.min_range..=self.attack_range).contains(&distance)
+    }
+}
+
+/// Defense shells explode on impact, damaging every unit around
+#[derive(Component, Debug, Clone)]
+pub struct SplashDamage {
+    pub radius: f32,
+}
+
+impl SplashDamage {
+    pub fn new(radius: f32) -> Self {
+        SplashDamage { radius }
+    }
+}
+
+/// Defense deals continuous damage instead of firing projectiles, the
+/// damage multiplier grows while it keeps the same target
+#[derive(Component, Debug, Clone)]
+pub struct BeamAttack {
+    /// Multiplier gained per second of continuous fire
+    pub ramp_rate: f32,
+    pub max_multiplier: f32,
+    pub multiplier: f32,
+    pub locked_on: Option<Entity>,
+}
+
+impl BeamAttack {
+    pub fn new(ramp_rate: f32, max_multiplier: f32) -> Self {
+        BeamAttack {
+            ramp_rate,
+            max_multiplier,
+            multiplier: 1.0,
+            locked_on: None,
+        }
+    }
+}
+
+/// Which units a defense is able to shoot at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DefenseTargeting {
+    Ground,
+    Air,
+    Both,
+}
+
+impl DefenseTargeting {
+    pub fn allows(&self, layer: UnitLayer) -> bool {
+        match self {
+            DefenseTargeting::Ground => layer == UnitLayer::Ground,
+            DefenseTargeting::Air => layer == UnitLayer::Air,
+            DefenseTargeting::Both => true,
         }
     }
 }
 
 #[derive(Component, Debug, Clone)]
-#[require(BuildingType(|| BuildingType::Wall))]
-pub struct Wall {
-    pub durability: f32,
+#[require(GameWorldMarker)]
+pub struct Projectile {
+    pub target: ProjectileTarget,
+    pub damage: f32,
+    pub speed: f32,
+}
+
+#[derive(Debug, Clone)]
+pub enum ProjectileTarget {
+    /// Homes in on a single unit
+    Unit(Entity),
+    /// Flies to a fixed point and damages every matching unit in the radius
+    Area {
+        position: Vec2,
+        radius: f32,
+        targeting: DefenseTargeting,
+    },
+}
+
+impl Projectile {
+    pub fn new(target: Entity, damage: f32) -> Self {
+        Projectile {
+            target: ProjectileTarget::Unit(target),
+            damage,
+            speed: PROJECTILE_SPEED,
+        }
+    }
+
+    pub fn new_area(position: Vec2, radius: f32, targeting: DefenseTargeting, damage: f32) -> Self {
+        Projectile {
+            target: ProjectileTarget::Area {
+                position,
+                radius,
+                targeting,
+            },
+            damage,
+            speed: SHELL_SPEED,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TrapKind {
+    /// Explodes damaging every ground unit around
+    Bomb,
+    /// Throws a single ground unit out of the battle
+    SpringTrap,
+    /// Big hit against a single flying unit
+    AirMine,
+}
+
+impl TrapKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TrapKind::Bomb => "Bomb",
+            TrapKind::SpringTrap => "Spring Trap",
+            TrapKind::AirMine => "Air Mine",
+        }
+    }
+}
+
+/// Hidden from attackers until it triggers, after that it stays
+/// disarmed until the owner pays to re-arm it
+#[derive(Component, Debug, Clone)]
+#[require(GameWorldMarker)]
+pub struct Trap {
+    pub kind: TrapKind,
+    pub damage: f32,
+    pub trigger_radius: f32,
+    /// Zero for traps that only hit the unit that triggered them
+    pub splash_radius: f32,
+    pub targeting: DefenseTargeting,
+    pub armed: bool,
+    pub rearm_cost: f64,
+}
+
+impl Trap {
+    pub fn new(
+        kind: TrapKind,
+        damage: f32,
+        trigger_radius: f32,
+        targeting: DefenseTargeting,
+        rearm_cost: f64,
+    ) -> Self {
+        Trap {
+            kind,
+            damage,
+            trigger_radius,
+            splash_radius: 0.0,
+            targeting,
+            armed: true,
+            rearm_cost,
+        }
+    }
+
+    pub fn with_splash_radius(mut self, splash_radius: f32) -> Self {
+        self.splash_radius = splash_radius;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum SpellType {
+    /// Instant damage to buildings in the area
+    Lightning,
+    /// Heals units standing in the zone
+    Heal,
+    /// Units in the zone hit harder and move faster
+    Rage,
+    /// Defenses in the zone stop shooting
+    Freeze,
 }
 
-impl Wall {
-    pub fn new(durability: f32) -> Self {
-        Wall { durability }
+impl SpellType {
+    pub const ALL: [SpellType; 4] = [
+        SpellType::Lightning,
+        SpellType::Heal,
+        SpellType::Rage,
+        SpellType::Freeze,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SpellType::Lightning => "Lightning",
+            SpellType::Heal => "Heal",
+            SpellType::Rage => "Rage",
+            SpellType::Freeze => "Freeze",
+        }
+    }
+
+    pub fn radius(&self) -> f32 {
+        match self {
+            SpellType::Lightning => LIGHTNING_RADIUS,
+            SpellType::Heal => HEAL_RADIUS,
+            SpellType::Rage => RAGE_RADIUS,
+            SpellType::Freeze => FREEZE_RADIUS,
+        }
     }
+
+    /// Seconds the zone stays on the battlefield
+    pub fn duration(&self) -> f32 {
+        match self {
+            SpellType::Lightning => 0.0,
+            SpellType::Heal => HEAL_DURATION,
+            SpellType::Rage => RAGE_DURATION,
+            SpellType::Freeze => FREEZE_DURATION,
+        }
+    }
+
+    pub fn brew_time(&self) -> f32 {
+        match self {
+            SpellType::Lightning | SpellType::Heal | SpellType::Rage => SPELL_BREW_TIME,
+            SpellType::Freeze => FREEZE_BREW_TIME,
+        }
+    }
+
+    /// Elixir paid when the spell is queued
+    pub fn cost(&self) -> f64 {
+        match self {
+            SpellType::Lightning => LIGHTNING_COST,
+            SpellType::Heal => HEAL_COST,
+            SpellType::Rage => RAGE_COST,
+            SpellType::Freeze => FREEZE_COST,
+        }
+    }
+}
+
+/// Brews queued spells one at a time into [`SpellInventory`]
+#[derive(Component, Debug, Clone)]
+#[require(BuildingType(|| BuildingType::SpellFactory))]
+pub struct SpellFactory {
+    /// Max spells brewed plus queued
+    pub capacity: u32,
+    pub queue: Vec<SpellType>,
+    /// Seconds spent on the first spell of the queue
+    pub progress: f32,
+}
+
+impl SpellFactory {
+    pub fn new(capacity: u32) -> Self {
+        SpellFactory {
+            capacity,
+            queue: Vec::new(),
+            progress: 0.0,
+        }
+    }
+}
+
+/// Area of effect left by a deployed spell
+#[derive(Component, Debug, Clone)]
+#[require(GameWorldMarker, GroundLayer)]
+pub struct SpellZone {
+    pub spell: SpellType,
+    pub radius: f32,
+    /// Seconds left, the zone applies its effect at least once
+    pub remaining: f32,
+}
+
+impl SpellZone {
+    pub fn new(spell: SpellType) -> Self {
+        SpellZone {
+            spell,
+            radius: spell.radius(),
+            remaining: spell.duration(),
+        }
+    }
+}
+
+// Timed spell effects, removed by [`tick_spell_effects`] once they run out
+
+#[derive(Component, Debug, Clone)]
+pub struct Healing {
+    pub per_second: f32,
+    pub remaining: f32,
+}
+
+#[derive(Component, Debug, Clone)]
+pub struct Raged {
+    pub damage_multiplier: f32,
+    pub speed_multiplier: f32,
+    pub remaining: f32,
+}
+
+#[derive(Component, Debug, Clone)]
+pub struct Frozen {
+    pub remaining: f32,
+}
+
+/// Wall health lives in [`Building`] like every other building
+#[derive(Component, Debug, Clone, Default)]
+#[require(BuildingType(|| BuildingType::Wall))]
+pub struct Wall {
+    /// Walls sharing an id are connected, see [`recompute_wall_segments`]
+    pub segment: usize,
 }
 
 #[derive(Component, Debug, Clone)]
+#[require(GameWorldMarker)]
 pub struct Building {
     pub level: u32,
     pub health: f32,
@@ -203,8 +657,69 @@ impl Building {
     }
 }
 
+/// Building reached 0 health during a battle, it no longer blocks the map
+/// and is restored when the battle ends
+#[derive(Component, Debug, Clone)]
+pub struct Destroyed;
+
+/// What is left of a destroyed building until the battle ends
+#[derive(Component, Debug, Clone)]
+#[require(GameWorldMarker, GroundLayer)]
+pub struct Rubble;
+
+/// Follows the health of a building or unit to show the damage it takes
+#[derive(Component, Debug, Clone)]
+pub struct HealthTracker {
+    /// Health seen on the last check
+    pub last: f32,
+    pub max: f32,
+    /// Damage not shown as a number yet
+    pub pending: f32,
+    /// Seconds since the last damage number
+    pub since_number: f32,
+    /// Seconds of hit flash left
+    pub flash: f32,
+    /// Material swapped out while flashing
+    pub material: Option<Handle<ColorMaterial>>,
+    /// Root and fill of the health bar, spawned on the first hit
+    pub bar: Option<(Entity, Entity)>,
+}
+
+impl HealthTracker {
+    pub fn new(health: f32, max: f32) -> Self {
+        HealthTracker {
+            last: health,
+            max,
+            pending: 0.0,
+            since_number: 0.0,
+            flash: 0.0,
+            material: None,
+            bar: None,
+        }
+    }
+}
+
+#[derive(Component, Debug, Clone)]
+pub struct HealthBarFill {
+    pub width: f32,
+}
+
+/// Floating text showing damage, fades out over its lifetime
+#[derive(Component, Debug, Clone)]
+#[require(GameWorldMarker)]
+pub struct DamageNumber {
+    pub remaining: f32,
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct BuildingDestroyed {
+    pub entity: Entity,
+    pub building_type: BuildingType,
+}
+
 // Unit components
 #[derive(Component, Debug)]
+#[require(UnitState, GameWorldMarker)]
 pub struct Unit {
     pub health: f32,
     pub max_health: f32,
@@ -213,6 +728,186 @@ pub struct Unit {
     pub attack_speed: f32,
     pub movement_speed: f32,
     pub target_preference: TargetPreference,
+    pub layer: UnitLayer,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnitLayer {
+    Ground,
+    Air,
+}
+
+#[derive(Component, Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum UnitType {
+    Barbarian,
+    WallBreaker,
+    Minion,
+    Hero(HeroType),
+}
+
+impl UnitType {
+    /// Regular troops, heroes are deployed from their altar
+    pub const ALL: [UnitType; 3] = [UnitType::Barbarian, UnitType::WallBreaker, UnitType::Minion];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            UnitType::Barbarian => "Barbarian",
+            UnitType::WallBreaker => "Wall Breaker",
+            UnitType::Minion => "Minion",
+            UnitType::Hero(hero) => hero.name(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HeroType {
+    /// Melee tank, the ability enrages him
+    King,
+    /// Ranged, the ability heals her
+    Queen,
+}
+
+impl HeroType {
+    pub const ALL: [HeroType; 2] = [HeroType::King, HeroType::Queen];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            HeroType::King => "King",
+            HeroType::Queen => "Queen",
+        }
+    }
+
+    pub fn max_health(&self, level: u32) -> f32 {
+        let idx = validate_level(level);
+        match self {
+            HeroType::King => KING_HEALTH[idx],
+            HeroType::Queen => QUEEN_HEALTH[idx],
+        }
+    }
+}
+
+/// Keeps the hero between battles, the hero can only leave the altar with
+/// full health and regenerates over real time
+#[derive(Component, Debug, Clone)]
+pub struct HeroAltar {
+    pub hero: HeroType,
+    pub level: u32,
+    pub health: f32,
+    /// Hero unit currently out of the altar, guarding or attacking
+    pub deployed: Option<Entity>,
+}
+
+impl HeroAltar {
+    pub fn new(hero: HeroType) -> Self {
+        HeroAltar {
+            hero,
+            level: 1,
+            health: hero.max_health(1),
+            deployed: None,
+        }
+    }
+
+    pub fn max_health(&self) -> f32 {
+        self.hero.max_health(self.level)
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.deployed.is_none() && self.health >= self.max_health()
+    }
+}
+
+#[derive(Component, Debug, Clone)]
+pub struct Hero {
+    pub hero_type: HeroType,
+    pub altar: Option<Entity>,
+    /// Seconds left until the ability can be used again
+    pub ability_cooldown: f32,
+}
+
+impl Hero {
+    pub fn new(hero_type: HeroType, altar: Option<Entity>) -> Self {
+        Hero {
+            hero_type,
+            altar,
+            ability_cooldown: 0.0,
+        }
+    }
+}
+
+/// Holds donated troops, they defend the castle or join an attack
+#[derive(Component, Debug, Clone)]
+#[require(BuildingType(|| BuildingType::ClanCastle))]
+pub struct ClanCastle {
+    pub capacity: u32,
+    pub troops: Vec<UnitType>,
+    pub trigger_radius: f32,
+}
+
+impl ClanCastle {
+    pub fn new(capacity: u32) -> Self {
+        ClanCastle {
+            capacity,
+            troops: Vec::new(),
+            trigger_radius: CLAN_CASTLE_TRIGGER_RADIUS,
+        }
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.troops.len() as u32 >= self.capacity
+    }
+}
+
+/// Unit fighting for the village, it chases attackers that come close to
+/// its post and walks back once they are gone
+#[derive(Component, Debug, Clone)]
+pub struct Defender {
+    pub post: Vec2,
+    pub guard_radius: f32,
+}
+
+impl Defender {
+    pub fn new(post: Vec2, guard_radius: f32) -> Self {
+        Defender { post, guard_radius }
+    }
+}
+
+/// What a unit is currently doing, driven by [`update_unit_targets`]
+#[derive(Component, Debug, Clone, Default)]
+pub struct UnitState {
+    pub target: Option<Entity>,
+    /// Tiles left to walk, the first one is the next step
+    pub path: Vec<UVec2>,
+    /// Seconds left until the next hit
+    pub cooldown: f32,
+    /// No path to the target was found, the unit doesn't look again until
+    /// something on the map falls
+    pub unreachable: bool,
+}
+
+impl UnitState {
+    /// Forces the unit to pick its target and path again
+    pub fn reset(&mut self) {
+        self.target = None;
+        self.reroute();
+    }
+
+    /// Forces the unit to look for a new path to its target
+    pub fn reroute(&mut self) {
+        self.path.clear();
+        self.unreachable = false;
+    }
+}
+
+/// Multiplies the damage the unit deals to walls
+#[derive(Component, Debug, Clone)]
+pub struct WallBreaker {
+    pub damage_multiplier: f32,
+}
+
+impl WallBreaker {
+    pub fn new(damage_multiplier: f32) -> Self {
+        WallBreaker { damage_multiplier }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -222,6 +917,92 @@ pub enum TargetPreference {
     ResourcesFirst,
 }
 
+impl TargetPreference {
+    /// Lower ranks are attacked first, ties are broken by distance
+    pub fn rank(&self, building_type: &BuildingType) -> u8 {
+        match (self, building_type) {
+            (TargetPreference::DefenseFirst, BuildingType::Defense(_)) => 0,
+            (
+                TargetPreference::ResourcesFirst,
+                BuildingType::Collector(_) | BuildingType::Storage(_),
+            ) => 0,
+            _ => 1,
+        }
+    }
+}
+
+// Brewed spells ready to be deployed in an attack
+#[derive(Resource, Debug, Default)]
+pub struct SpellInventory {
+    pub spells: HashMap<SpellType, u32>,
+}
+
+impl SpellInventory {
+    pub fn count(&self) -> u32 {
+        self.spells.values().sum()
+    }
+
+    /// Takes one spell out of the inventory, false if there was none
+    pub fn take(&mut self, spell: SpellType) -> bool {
+        match self.spells.get_mut(&spell) {
+            Some(amount) if *amount > 0 => {
+                *amount -= 1;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Local stand-in for an online clan, the members donate from a fixed
+/// roster so requests get filled offline too
+#[derive(Resource, Debug, Clone)]
+pub struct Clan {
+    pub name: String,
+    /// Troops the members donate, in turn
+    pub roster: Vec<UnitType>,
+    pub next_donor: usize,
+    pub requested: bool,
+    /// Seconds until the next donation of the open request
+    pub donation_timer: f32,
+}
+
+impl Default for Clan {
+    fn default() -> Self {
+        Clan {
+            name: "Local Clan".into(),
+            roster: vec![
+                UnitType::Barbarian,
+                UnitType::Barbarian,
+                UnitType::WallBreaker,
+                UnitType::Minion,
+            ],
+            next_donor: 0,
+            requested: false,
+            donation_timer: DONATION_INTERVAL,
+        }
+    }
+}
+
+impl Clan {
+    /// Opens a request, troops arrive one by one until the castles are full
+    pub fn request(&mut self) {
+        if !self.requested {
+            self.requested = true;
+            self.donation_timer = DONATION_INTERVAL;
+        }
+    }
+
+    /// Next troop donated, none if nobody is left in the clan
+    pub fn donate(&mut self) -> Option<UnitType> {
+        let troop = *self
+            .roster
+            .get(self.next_donor % self.roster.len().max(1))?;
+        self.next_donor += 1;
+        Some(troop)
+    }
+}
+
 // Global resource for player resources
 #[derive(Resource, Debug, Default)]
 pub struct PlayerResources {
@@ -233,6 +1014,8 @@ pub struct BuildingAssets {
     pub default: Handles,
     #[deref]
     pub map: HashMap<BuildingType, Handles>,
+    /// Art replacing the colored meshes, when there is some
+    pub atlas: Option<BuildingAtlas>,
 }
 
 impl BuildingAssets {
@@ -246,6 +1029,63 @@ impl BuildingAssets {
     pub fn get(&self, building_type: &BuildingType) -> &Handles {
         self.map.get(building_type).unwrap_or(&self.default)
     }
+
+    /// Sprite for the building at this level, `None` means the mesh is used
+    pub fn sprite(&self, building_type: &BuildingType, level: u32, size: Vec2) -> Option<Sprite> {
+        self.atlas.as_ref()?.sprite(building_type, level, size)
+    }
+}
+
+/// Building art still loading, see [`load_building_atlas`]
+#[derive(Resource, Debug)]
+pub struct PendingBuildingAtlas(pub Handle<Image>);
+
+/// Building sprites in a texture atlas, per building type and level
+#[derive(Debug, Clone)]
+pub struct BuildingAtlas {
+    pub image: Handle<Image>,
+    pub layout: Handle<TextureAtlasLayout>,
+    pub indices: HashMap<(BuildingType, u32), usize>,
+}
+
+impl BuildingAtlas {
+    /// Atlas with a row per building type, at [`BuildingType::atlas_row`], and
+    /// a column per level
+    pub fn from_rows(
+        image: Handle<Image>,
+        layout: Handle<TextureAtlasLayout>,
+        levels: u32,
+    ) -> Self {
+        let mut indices = HashMap::new();
+        for building_type in BuildingType::ALL {
+            let row = building_type.atlas_row();
+            for level in 1..=levels {
+                let index = (row * levels + level - 1) as usize;
+                indices.insert((building_type, level), index);
+            }
+        }
+        BuildingAtlas {
+            image,
+            layout,
+            indices,
+        }
+    }
+
+    /// Missing levels use the art of the closest lower level
+    pub fn sprite(&self, building_type: &BuildingType, level: u32, size: Vec2) -> Option<Sprite> {
+        let index = (1..=level)
+            .rev()
+            .find_map(|level| self.indices.get(&(*building_type, level)))?;
+        Some(Sprite {
+            image: self.image.clone(),
+            texture_atlas: Some(TextureAtlas {
+                layout: self.layout.clone(),
+                index: *index,
+            }),
+            custom_size: Some(size),
+            ..default()
+        })
+    }
 }
 
 #[derive(Default, Debug, Hash, PartialEq)]
@@ -270,39 +1110,342 @@ impl Handles {
     }
 }
 
+#[derive(Resource, Deref)]
+pub struct ProjectileAssets(pub Handles);
+
+#[derive(Default, Resource, Deref, DerefMut)]
+pub struct SpellAssets(pub HashMap<SpellType, Handles>);
+
+#[derive(Resource, Debug)]
+pub struct FeedbackAssets {
+    pub flash: Handle<ColorMaterial>,
+    /// Unit square, scaled to the size of the building
+    pub rubble: Handles,
+}
+
+#[derive(Default, Resource, Deref, DerefMut)]
+pub struct UnitAssets {
+    pub default: Handles,
+    #[deref]
+    pub map: HashMap<UnitType, Handles>,
+}
+
+impl UnitAssets {
+    pub fn new(default: Handles) -> Self {
+        UnitAssets {
+            default,
+            ..Default::default()
+        }
+    }
+
+    pub fn get(&self, unit_type: &UnitType) -> &Handles {
+        self.map.get(unit_type).unwrap_or(&self.default)
+    }
+}
+
 // Marker for selected entity
 #[derive(Component)]
 pub struct Selected;
 
+/// What the next session starts from, picked in the main menu
+#[derive(Resource, Debug, Clone, Default)]
+pub enum SessionStart {
+    #[default]
+    NewGame,
+    Continue(SaveGame),
+    /// Empty map in the editor, apart from the village and its save
+    Sandbox,
+}
+
+/// State the pause menu goes back to
+#[derive(Resource, Debug)]
+pub struct PauseState {
+    pub resume_to: GameState,
+}
+
+#[derive(Resource, Default, Debug)]
+pub struct SelectionState {
+    /// The next click moves the selected building there
+    pub moving: bool,
+}
+
 #[derive(Component)]
+#[require(GameWorldMarker, GroundLayer)]
 pub struct GridVisual;
 
+/// Lies flat on the ground, the isometric view squashes it into a diamond
+/// instead of only moving it
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct GroundLayer;
+
+/// How the camera catches up with its pan and zoom targets
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CameraEasing {
+    /// Jumps straight to the target
+    Snap,
+    /// Closes the gap exponentially, the value is the rate per second
+    Exponential(f32),
+}
+
+impl CameraEasing {
+    /// Fraction of the remaining gap to close this frame
+    pub fn factor(&self, delta_secs: f32) -> f32 {
+        match self {
+            CameraEasing::Snap => 1.0,
+            CameraEasing::Exponential(rate) => 1.0 - (-rate * delta_secs).exp(),
+        }
+    }
+}
+
+/// How the grid is looked at. Transforms stay in grid space, so the
+/// simulation and cursor picks don't care; [`project_view`] projects the
+/// global transforms in `PostUpdate`, after transform propagation
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ViewMode {
+    #[default]
+    TopDown,
+    Isometric,
+}
+
+impl ViewMode {
+    pub fn toggled(self) -> Self {
+        match self {
+            ViewMode::TopDown => ViewMode::Isometric,
+            ViewMode::Isometric => ViewMode::TopDown,
+        }
+    }
+
+    /// Grid to screen space, in iso grid x points down-right and y up-right
+    pub fn matrix(self) -> Mat2 {
+        match self {
+            ViewMode::TopDown => Mat2::IDENTITY,
+            ViewMode::Isometric => {
+                let half = ISO_TILE_SIZE / 2.0;
+                Mat2::from_cols(Vec2::new(half.x, -half.y), half)
+            }
+        }
+    }
+
+    /// Where a grid position is drawn
+    pub fn project(self, grid: Vec2) -> Vec2 {
+        self.matrix() * grid
+    }
+
+    /// Grid position drawn at a point on screen, for picking
+    pub fn unproject(self, screen: Vec2) -> Vec2 {
+        self.matrix().inverse() * screen
+    }
+
+    /// Half sizes of the ellipse a circle on the ground is drawn as
+    pub fn ground_radii(self, radius: f32) -> Vec2 {
+        match self {
+            ViewMode::TopDown => Vec2::splat(radius),
+            ViewMode::Isometric => ISO_TILE_SIZE * std::f32::consts::FRAC_1_SQRT_2 * radius,
+        }
+    }
+
+    /// Draw depth of a building, in iso the ones further back go first
+    pub fn building_z(self, position: &GridPosition, size: &GridSize) -> f32 {
+        match self {
+            ViewMode::TopDown => BUILDING_Z,
+            ViewMode::Isometric => {
+                // Grid y points up-right and x down-right on screen
+                let depth = (position.y as f32 + size.height as f32 / 2.0)
+                    - (position.x as f32 + size.width as f32 / 2.0);
+                BUILDING_Z - depth * ISO_DEPTH_STEP
+            }
+        }
+    }
+}
+
+/// A left click that didn't turn into a camera drag
+#[derive(Event, Debug, Clone, Copy)]
+pub struct WorldClick {
+    pub position: Vec2,
+}
+
+/// Tile picked with the d-pad, hidden while the mouse is in use
+#[derive(Resource, Debug, Default)]
+pub struct GridCursor {
+    pub tile: Option<IVec2>,
+}
+
 #[derive(Component)]
 pub struct GameCamera {
-    pub speed: f32,
+    /// Set once the pointer moved further than `drag_threshold` while pressed
     pub is_dragging: bool,
-    pub last_position: Option<Vec2>,
+    /// Screen position where the button went down
+    pub press_position: Option<Vec2>,
+    /// World point under the pointer when the button went down
+    pub grab: Option<Vec2>,
+    /// Pointer travel in pixels before a press counts as a drag
+    pub drag_threshold: f32,
     pub zoom: f32,
     pub min_zoom: f32,
     pub max_zoom: f32,
     pub zoom_speed: f32,
+    /// Position the camera eases toward
+    pub target: Vec2,
+    pub target_zoom: f32,
+    /// World point and its screen offset from the center, kept in place
+    /// until the zoom reaches its target
+    pub zoom_anchor: Option<(Vec2, Vec2)>,
+    pub easing: CameraEasing,
+    /// WASD and arrow keys panning
+    pub key_pan: bool,
+    /// Panning while the cursor touches the window border
+    pub edge_pan: bool,
+    /// Width of the border that triggers edge panning, in pixels
+    pub edge_margin: f32,
+    /// Key and edge panning speed, in screen pixels per second
+    pub pan_speed: f32,
+    /// How far the viewport may show past the map edge, in world units
+    pub bounds_padding: f32,
+    /// Max stretch when dragging past the bounds, in screen pixels
+    pub elastic_distance: f32,
+    /// Pan speed in world units per second left from a touch fling
+    pub velocity: Vec2,
+    /// How fast the fling slows down, per second
+    pub friction: f32,
 }
 
 impl Default for GameCamera {
     fn default() -> Self {
         Self {
-            speed: 20.0,
             is_dragging: false,
-            last_position: None,
+            press_position: None,
+            grab: None,
+            drag_threshold: 6.0,
             zoom: 6.0,
             min_zoom: 4.0,
             max_zoom: 20.0,
             zoom_speed: 1.0,
+            target: Vec2::ZERO,
+            target_zoom: 6.0,
+            zoom_anchor: None,
+            easing: CameraEasing::Exponential(12.0),
+            key_pan: true,
+            edge_pan: true,
+            edge_margin: 8.0,
+            pan_speed: 600.0,
+            bounds_padding: 5.0,
+            elastic_distance: 120.0,
+            velocity: Vec2::ZERO,
+            friction: 5.0,
         }
     }
 }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Deployable {
+    Unit(UnitType),
+    Spell(SpellType),
+    /// Every troop of the clan castle at once
+    Reinforcements,
+}
+
+#[derive(Resource, Default, Debug)]
+pub struct AttackState {
+    pub selected: Option<Deployable>,
+    pub is_selected: bool,
+    /// Heroes can only be deployed once per attack
+    pub deployed_heroes: Vec<HeroType>,
+}
+
 #[derive(Resource, Default, Debug)]
 pub struct EditorState {
     pub selected_building: Option<BuildingType>,
     pub is_selected: bool,
+    /// Attackers the coverage heatmap counts defenses for, hidden when `None`
+    pub coverage: Option<UnitLayer>,
+    /// Buildings placed since the editor opened, most recent last
+    pub history: Vec<Entity>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit(layer: UnitLayer) -> Unit {
+        Unit {
+            health: 10.0,
+            max_health: 10.0,
+            attack_damage: 1.0,
+            attack_range: 1.0,
+            attack_speed: 1.0,
+            movement_speed: 1.0,
+            target_preference: TargetPreference::AnyBuilding,
+            layer,
+        }
+    }
+
+    #[test]
+    fn defense_targets_its_layer_only() {
+        let mortar = Defense::new_with_targeting(10.0, 5.0, 1.0, DefenseTargeting::Ground);
+        let air_defense = Defense::new_with_targeting(10.0, 5.0, 1.0, DefenseTargeting::Air);
+        let tower = Defense::new(10.0, 5.0, 1.0);
+
+        assert!(mortar.can_target(&unit(UnitLayer::Ground), 3.0));
+        assert!(!mortar.can_target(&unit(UnitLayer::Air), 3.0));
+        assert!(air_defense.can_target(&unit(UnitLayer::Air), 3.0));
+        assert!(!air_defense.can_target(&unit(UnitLayer::Ground), 3.0));
+        assert!(tower.can_target(&unit(UnitLayer::Air), 3.0));
+        assert!(tower.can_target(&unit(UnitLayer::Ground), 3.0));
+    }
+
+    #[test]
+    fn defense_misses_inside_min_range_and_past_range() {
+        let mortar = Defense::new(10.0, 5.0, 1.0).with_min_range(2.0);
+        let barbarian = unit(UnitLayer::Ground);
+
+        assert!(!mortar.can_target(&barbarian, 1.9));
+        assert!(mortar.can_target(&barbarian, 2.0));
+        assert!(mortar.can_target(&barbarian, 5.0));
+        assert!(!mortar.can_target(&barbarian, 5.1));
+
+        let dead = Unit {
+            health: 0.0,
+            ..barbarian
+        };
+        assert!(!mortar.can_target(&dead, 3.0));
+    }
+
+    #[test]
+    fn spell_inventory_takes_until_empty() {
+        let mut inventory = SpellInventory {
+            spells: HashMap::from([(SpellType::Heal, 2)]),
+        };
+
+        assert!(inventory.take(SpellType::Heal));
+        assert!(inventory.take(SpellType::Heal));
+        assert!(!inventory.take(SpellType::Heal));
+        assert!(!inventory.take(SpellType::Rage));
+        assert_eq!(inventory.count(), 0);
+    }
+
+    #[test]
+    fn tile_map_frees_removed_tiles() {
+        let building = Entity::from_raw(1);
+        let mut map = TileMap::new(10, 10);
+        map.place(2, 2, building, (3, 3));
+
+        assert!(!map.can_place(4, 4, (2, 2)));
+        assert!(map.can_place(5, 5, (2, 2)));
+        assert!(!map.can_place(9, 9, (2, 2)), "out of bounds");
+
+        map.remove(2, 2, (3, 3));
+        assert!(map.can_place(2, 2, (3, 3)));
+        assert_eq!(map.get_entity_at(3, 3), None);
+    }
+
+    #[test]
+    fn tile_map_remove_entity_keeps_neighbours() {
+        let (left, right) = (Entity::from_raw(1), Entity::from_raw(2));
+        let mut map = TileMap::new(10, 10);
+        map.place(0, 0, left, (2, 2));
+        map.place(2, 0, right, (2, 2));
+
+        map.remove_entity(0, 0, left, (4, 2));
+        assert_eq!(map.get_entity_at(1, 1), None);
+        assert_eq!(map.get_entity_at(2, 0), Some(right));
+    }
 }
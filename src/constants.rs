@@ -1,4 +1,6 @@
+use crate::components::UnitType;
 use bevy::prelude::*;
+use std::f32::consts::{FRAC_1_SQRT_2, SQRT_2};
 
 // Rectangle sizes
 pub const DEFAULT_SIZE: Vec2 = Vec2::new(1.0, 1.0);
@@ -6,14 +8,46 @@ pub const TOWNHALL_SIZE: Vec2 = Vec2::new(4.0, 4.0);
 pub const COLLECTOR_SIZE: Vec2 = Vec2::new(3.0, 3.0);
 pub const STORAGE_SIZE: Vec2 = Vec2::new(4.0, 4.0);
 pub const DEFENSE_SIZE: Vec2 = Vec2::new(3.0, 3.0);
+pub const MORTAR_SIZE: Vec2 = Vec2::new(3.0, 3.0);
+pub const AIR_DEFENSE_SIZE: Vec2 = Vec2::new(3.0, 3.0);
+pub const INFERNO_SIZE: Vec2 = Vec2::new(2.0, 2.0);
 pub const WALL_SIZE: Vec2 = Vec2::new(1.0, 1.0);
+pub const TRAP_SIZE: Vec2 = Vec2::new(1.0, 1.0);
+pub const SPELL_FACTORY_SIZE: Vec2 = Vec2::new(3.0, 3.0);
+pub const HERO_ALTAR_SIZE: Vec2 = Vec2::new(3.0, 3.0);
+pub const CLAN_CASTLE_SIZE: Vec2 = Vec2::new(3.0, 3.0);
+pub const HERO_SIZE: f32 = 0.5;
+pub const PROJECTILE_SIZE: f32 = 0.2;
+pub const UNIT_SIZE: f32 = 0.35;
 
 // Colors
 pub const TOWNHALL_COLOR: Color = Color::srgb(0.0, 1.0, 0.0);
 pub const ELIXIR_COLOR: Color = Color::srgb(1.0, 0.0, 1.0);
 pub const GOLD_COLOR: Color = Color::srgb(1.0, 0.84, 0.0);
 pub const DEFENSE_COLOR: Color = Color::srgb(1.0, 0.0, 0.0);
+pub const MORTAR_COLOR: Color = Color::srgb(0.55, 0.27, 0.07);
+pub const AIR_DEFENSE_COLOR: Color = Color::srgb(0.0, 0.6, 1.0);
+pub const INFERNO_COLOR: Color = Color::srgb(1.0, 0.45, 0.0);
 pub const WALL_COLOR: Color = Color::srgb(0.6, 0.3, 0.2);
+pub const BOMB_COLOR: Color = Color::srgb(0.2, 0.2, 0.2);
+pub const SPRING_TRAP_COLOR: Color = Color::srgb(0.5, 0.5, 0.6);
+pub const AIR_MINE_COLOR: Color = Color::srgb(0.6, 0.8, 1.0);
+pub const PROJECTILE_COLOR: Color = Color::srgb(0.1, 0.1, 0.1);
+pub const SPELL_FACTORY_COLOR: Color = Color::srgb(0.5, 0.0, 0.6);
+pub const LIGHTNING_COLOR: Color = Color::srgba(0.6, 0.8, 1.0, 0.5);
+pub const HEAL_COLOR: Color = Color::srgba(1.0, 0.9, 0.3, 0.3);
+pub const RAGE_COLOR: Color = Color::srgba(0.7, 0.0, 0.8, 0.3);
+pub const FREEZE_COLOR: Color = Color::srgba(0.4, 0.9, 1.0, 0.3);
+pub const KING_COLOR: Color = Color::srgb(0.9, 0.6, 0.1);
+pub const QUEEN_COLOR: Color = Color::srgb(0.2, 0.7, 0.3);
+pub const CLAN_CASTLE_COLOR: Color = Color::srgb(0.6, 0.3, 0.2);
+pub const MINIMAP_BACKGROUND: Color = Color::srgb(0.2, 0.35, 0.15);
+pub const MINIMAP_ATTACKER_COLOR: Color = Color::srgb(1.0, 0.1, 0.1);
+pub const MINIMAP_DEFENDER_COLOR: Color = Color::srgb(0.2, 0.6, 1.0);
+pub const BARBARIAN_COLOR: Color = Color::srgb(1.0, 0.9, 0.4);
+pub const WALL_BREAKER_COLOR: Color = Color::srgb(0.9, 0.9, 0.9);
+pub const MINION_COLOR: Color = Color::srgb(0.3, 0.3, 0.9);
+pub const BEAM_COLOR: Color = Color::srgb(1.0, 0.3, 0.0);
 
 pub const BROWN_0: Color = Color::srgb(0.102, 0.059, 0.000);
 pub const BROWN_1: Color = Color::srgb(0.200, 0.118, 0.000);
@@ -56,6 +90,13 @@ pub const COLLECTOR_HEALTH: [f32; 10] = [
     400.0, 450.0, 500.0, 550.0, 600.0, 650.0, 700.0, 750.0, 800.0, 900.0,
 ];
 pub const COLLECTOR_RATE: [f32; 10] = [5.0, 7.0, 9.0, 12.0, 15.0, 18.0, 22.0, 26.0, 30.0, 35.0];
+pub const COLLECTOR_CAPACITY: [f32; 10] = [
+    500.0, 1000.0, 1500.0, 2000.0, 3000.0, 4000.0, 5000.0, 6500.0, 8000.0, 10000.0,
+];
+/// Gold to upgrade a building from the level at this index
+pub const BUILDING_UPGRADE_COST: [f64; 10] = [
+    250.0, 500.0, 1000.0, 2000.0, 4000.0, 8000.0, 16000.0, 32000.0, 64000.0, 0.0,
+];
 pub const STORAGE_HEALTH: [f32; 10] = [
     600.0, 700.0, 800.0, 900.0, 1000.0, 1100.0, 1200.0, 1300.0, 1400.0, 1500.0,
 ];
@@ -68,10 +109,249 @@ pub const DEFENSE_HEALTH: [f32; 10] = [
 pub const DEFENSE_DAMAGE: [f32; 10] = [20.0, 25.0, 30.0, 35.0, 40.0, 45.0, 50.0, 55.0, 60.0, 70.0];
 pub const DEFENSE_RANGE: [f32; 10] = [5.0, 5.5, 6.0, 6.5, 7.0, 7.5, 8.0, 8.5, 9.0, 10.0];
 pub const DEFENSE_SPEED: [f32; 10] = [1.0, 1.1, 1.2, 1.3, 1.4, 1.5, 1.6, 1.7, 1.8, 2.0];
+pub const MORTAR_HEALTH: [f32; 10] = [
+    400.0, 450.0, 500.0, 550.0, 600.0, 650.0, 700.0, 800.0, 900.0, 1000.0,
+];
+pub const MORTAR_DAMAGE: [f32; 10] = [
+    40.0, 45.0, 50.0, 60.0, 70.0, 80.0, 90.0, 100.0, 110.0, 120.0,
+];
+pub const MORTAR_RANGE: [f32; 10] = [11.0; 10];
+pub const MORTAR_SPEED: [f32; 10] = [0.2; 10];
+pub const MORTAR_MIN_RANGE: f32 = 4.0;
+pub const MORTAR_SPLASH_RADIUS: f32 = 1.5;
+pub const AIR_DEFENSE_HEALTH: [f32; 10] = [
+    800.0, 850.0, 900.0, 950.0, 1000.0, 1050.0, 1100.0, 1200.0, 1300.0, 1400.0,
+];
+pub const AIR_DEFENSE_DAMAGE: [f32; 10] = [
+    80.0, 110.0, 140.0, 160.0, 190.0, 230.0, 280.0, 320.0, 360.0, 400.0,
+];
+pub const AIR_DEFENSE_RANGE: [f32; 10] = [10.0; 10];
+pub const AIR_DEFENSE_SPEED: [f32; 10] = [1.0; 10];
+pub const INFERNO_HEALTH: [f32; 10] = [
+    1500.0, 1800.0, 2100.0, 2400.0, 2700.0, 3000.0, 3300.0, 3700.0, 4000.0, 4500.0,
+];
+// Damage per second before the ramp up
+pub const INFERNO_DAMAGE: [f32; 10] = [30.0, 35.0, 40.0, 45.0, 50.0, 55.0, 60.0, 65.0, 70.0, 80.0];
+pub const INFERNO_RANGE: [f32; 10] = [9.0; 10];
+pub const INFERNO_RAMP_RATE: f32 = 0.5;
+pub const INFERNO_MAX_MULTIPLIER: f32 = 10.0;
 pub const WALL_HEALTH: [f32; 10] = [
     300.0, 400.0, 500.0, 600.0, 700.0, 800.0, 900.0, 1000.0, 1200.0, 1500.0,
 ];
+pub const BOMB_DAMAGE: [f32; 10] = [20.0, 24.0, 29.0, 35.0, 42.0, 54.0, 65.0, 75.0, 85.0, 95.0];
+pub const BOMB_TRIGGER_RADIUS: f32 = 1.5;
+pub const BOMB_SPLASH_RADIUS: f32 = 3.0;
+pub const BOMB_REARM_COST: f64 = 50.0;
+pub const SPRING_TRAP_TRIGGER_RADIUS: f32 = 0.8;
+pub const SPRING_TRAP_REARM_COST: f64 = 100.0;
+pub const AIR_MINE_DAMAGE: [f32; 10] = [
+    100.0, 120.0, 144.0, 173.0, 208.0, 250.0, 300.0, 360.0, 420.0, 500.0,
+];
+pub const AIR_MINE_TRIGGER_RADIUS: f32 = 4.0;
+pub const AIR_MINE_REARM_COST: f64 = 400.0;
+pub const SPELL_FACTORY_HEALTH: [f32; 10] = [
+    425.0, 470.0, 520.0, 600.0, 720.0, 840.0, 960.0, 1080.0, 1200.0, 1320.0,
+];
+pub const SPELL_FACTORY_CAPACITY: [u32; 10] = [2, 4, 6, 8, 10, 11, 11, 11, 11, 11];
+pub const HERO_ALTAR_HEALTH: [f32; 10] = [250.0; 10];
+pub const CLAN_CASTLE_HEALTH: [f32; 10] = [
+    1000.0, 1400.0, 2000.0, 2600.0, 3000.0, 3400.0, 4000.0, 4400.0, 4800.0, 5200.0,
+];
+/// Donated troops a castle can hold
+pub const CLAN_CASTLE_CAPACITY: [u32; 10] = [4, 6, 8, 10, 12, 14, 16, 18, 20, 22];
+
+// Spells
+pub const SPELL_BREW_TIME: f32 = 30.0;
+pub const FREEZE_BREW_TIME: f32 = 60.0;
+pub const SPELL_Z: f32 = 3.0;
+/// Seconds a spell effect lasts after the entity leaves the zone
+pub const SPELL_LINGER: f32 = 0.3;
+pub const LIGHTNING_RADIUS: f32 = 2.0;
+pub const LIGHTNING_DAMAGE: f32 = 300.0;
+pub const LIGHTNING_COST: f64 = 150.0;
+pub const HEAL_RADIUS: f32 = 5.0;
+pub const HEAL_DURATION: f32 = 12.0;
+pub const HEAL_PER_SECOND: f32 = 25.0;
+pub const HEAL_COST: f64 = 150.0;
+pub const RAGE_RADIUS: f32 = 5.0;
+pub const RAGE_DURATION: f32 = 18.0;
+pub const RAGE_DAMAGE_MULTIPLIER: f32 = 2.3;
+pub const RAGE_SPEED_MULTIPLIER: f32 = 1.5;
+pub const RAGE_COST: f64 = 230.0;
+pub const FREEZE_RADIUS: f32 = 3.5;
+pub const FREEZE_DURATION: f32 = 5.0;
+pub const FREEZE_COST: f64 = 260.0;
+
+// Constants for unit stats by level
+pub const BARBARIAN_HEALTH: [f32; 10] = [
+    45.0, 54.0, 65.0, 85.0, 105.0, 125.0, 160.0, 205.0, 230.0, 250.0,
+];
+pub const BARBARIAN_DAMAGE: [f32; 10] = [9.0, 12.0, 15.0, 18.0, 23.0, 26.0, 30.0, 34.0, 38.0, 42.0];
+pub const BARBARIAN_RANGE: f32 = 0.6;
+pub const BARBARIAN_SPEED: f32 = 1.0;
+pub const BARBARIAN_MOVEMENT: f32 = 2.0;
+pub const WALL_BREAKER_HEALTH: [f32; 10] =
+    [20.0, 24.0, 29.0, 35.0, 53.0, 72.0, 82.0, 92.0, 112.0, 130.0];
+pub const WALL_BREAKER_DAMAGE: [f32; 10] =
+    [6.0, 10.0, 15.0, 20.0, 43.0, 55.0, 66.0, 75.0, 86.0, 94.0];
+pub const WALL_BREAKER_RANGE: f32 = 0.6;
+pub const WALL_BREAKER_SPEED: f32 = 1.0;
+pub const WALL_BREAKER_MOVEMENT: f32 = 3.0;
+pub const WALL_BREAKER_MULTIPLIER: f32 = 40.0;
+pub const MINION_HEALTH: [f32; 10] = [
+    58.0, 64.0, 70.0, 76.0, 84.0, 92.0, 100.0, 108.0, 116.0, 124.0,
+];
+pub const MINION_DAMAGE: [f32; 10] = [38.0, 41.0, 44.0, 47.0, 50.0, 54.0, 58.0, 62.0, 66.0, 70.0];
+pub const MINION_RANGE: f32 = 2.75;
+pub const MINION_SPEED: f32 = 1.0;
+pub const MINION_MOVEMENT: f32 = 4.0;
+
+// Heroes
+pub const KING_HEALTH: [f32; 10] = [
+    1700.0, 1742.0, 1786.0, 1830.0, 1876.0, 1923.0, 1971.0, 2020.0, 2071.0, 2123.0,
+];
+pub const KING_DAMAGE: [f32; 10] = [
+    102.0, 104.0, 106.0, 108.0, 110.0, 112.0, 114.0, 116.0, 118.0, 120.0,
+];
+pub const KING_RANGE: f32 = 1.0;
+pub const KING_MOVEMENT: f32 = 2.0;
+pub const QUEEN_HEALTH: [f32; 10] = [
+    725.0, 740.0, 755.0, 771.0, 787.0, 804.0, 821.0, 838.0, 856.0, 874.0,
+];
+pub const QUEEN_DAMAGE: [f32; 10] = [
+    136.0, 139.0, 142.0, 145.0, 148.0, 152.0, 155.0, 158.0, 162.0, 165.0,
+];
+pub const QUEEN_RANGE: f32 = 5.0;
+pub const QUEEN_MOVEMENT: f32 = 2.4;
+pub const HERO_ATTACK_SPEED: f32 = 1.0;
+pub const HERO_UPGRADE_COST: [f64; 10] = [
+    500.0, 1000.0, 2000.0, 4000.0, 8000.0, 12000.0, 16000.0, 20000.0, 25000.0, 30000.0,
+];
+/// Health regained per real second while resting in the altar
+pub const HERO_REGEN_PER_SECOND: f32 = 5.0;
+pub const HERO_ABILITY_COOLDOWN: f32 = 30.0;
+pub const HERO_ABILITY_DURATION: f32 = 10.0;
+pub const HERO_GUARD_RADIUS: f32 = 8.0;
+pub const QUEEN_ABILITY_HEAL_PER_SECOND: f32 = 60.0;
+
+// Clan
+/// Attackers closer than this to the castle make its troops sally out
+pub const CLAN_CASTLE_TRIGGER_RADIUS: f32 = 10.0;
+/// Seconds between two donations while a request is open
+pub const DONATION_INTERVAL: f32 = 1.0;
+
+// Raids
+/// Troops attacking the village in a raid
+pub const RAID_TROOPS: [(UnitType, usize); 3] = [
+    (UnitType::Barbarian, 8),
+    (UnitType::WallBreaker, 2),
+    (UnitType::Minion, 3),
+];
+/// Raiders start this many tiles outside the village
+pub const RAID_SPAWN_MARGIN: f32 = 6.0;
+
+// Damage feedback
+pub const HEALTH_BAR_HEIGHT: f32 = 0.25;
+/// Relative to the building or unit, keeps the bar on top
+pub const HEALTH_BAR_Z: f32 = 5.0;
+pub const HEALTH_BAR_COLOR: Color = Color::srgb(0.2, 0.9, 0.2);
+pub const HEALTH_BAR_BACKGROUND: Color = Color::srgb(0.3, 0.0, 0.0);
+pub const HIT_FLASH_DURATION: f32 = 0.1;
+pub const HIT_FLASH_COLOR: Color = Color::srgb(1.0, 1.0, 1.0);
+/// Damage is summed up for this many seconds into one number
+pub const DAMAGE_NUMBER_INTERVAL: f32 = 0.25;
+pub const DAMAGE_NUMBER_DURATION: f32 = 0.8;
+/// Rise speed of the damage numbers, in tiles per second
+pub const DAMAGE_NUMBER_SPEED: f32 = 1.5;
+/// Text scale, the font size is in pixels and the world in tiles
+pub const DAMAGE_NUMBER_SCALE: f32 = 0.04;
+pub const DAMAGE_NUMBER_Z: f32 = 10.0;
+pub const DAMAGE_NUMBER_COLOR: Color = Color::srgb(1.0, 0.9, 0.2);
+pub const RUBBLE_COLOR: Color = Color::srgb(0.35, 0.3, 0.25);
+pub const RUBBLE_Z: f32 = 0.5;
+
+// Focus navigation
+pub const FOCUS_COLOR: Color = Color::srgb(1.0, 0.85, 0.2);
+pub const FOCUS_OUTLINE: Val = Val::Px(3.0);
+
+// Saves
+pub const SAVE_PATH: &str = "village.ron";
+pub const SETTINGS_PATH: &str = "settings.ron";
+
+// Selection
+pub const SELECTION_COLOR: Color = Color::srgb(1.0, 1.0, 0.3);
+pub const RANGE_COLOR: Color = Color::srgba(1.0, 1.0, 1.0, 0.6);
+pub const MIN_RANGE_COLOR: Color = Color::srgba(1.0, 0.3, 0.3, 0.6);
+
+// Coverage heatmap
+pub const COVERAGE_Z: f32 = 0.3;
+pub const UNCOVERED_COLOR: Color = Color::srgba(1.0, 0.0, 0.0, 0.35);
+pub const COVERED_COLOR: Color = Color::srgb(0.1, 0.9, 0.2);
+/// Opacity added per defense covering a tile
+pub const COVERAGE_ALPHA_STEP: f32 = 0.12;
+pub const COVERAGE_MAX_ALPHA: f32 = 0.6;
+
+// Grid
+pub const GRID_LINE_WIDTH: f32 = 0.1;
+pub const GRID_COLOR: Color = Color::srgba(0.5, 0.5, 0.5, 0.3);
+
+// Art
+/// Building atlas inside the assets folder, see
+/// [`BuildingAtlas::from_rows`](crate::components::BuildingAtlas::from_rows)
+pub const BUILDING_ATLAS_PATH: &str = "buildings.png";
+/// Side of one building sprite in the atlas, in pixels
+pub const BUILDING_SPRITE_SIZE: u32 = 128;
+pub const BUILDING_ATLAS_LEVELS: u32 = 10;
+/// One row per building type, see
+/// [`BuildingType::atlas_row`](crate::components::BuildingType::atlas_row)
+pub const BUILDING_ATLAS_ROWS: u32 = 17;
+
+// Input
+/// Wheel steps per second while a zoom action is held
+pub const KEY_ZOOM_STEPS: f32 = 4.0;
+/// Stick and trigger values below this are treated as resting
+pub const GAMEPAD_DEADZONE: f32 = 0.15;
+/// Seconds a cursor direction is held before it starts repeating
+pub const CURSOR_REPEAT_DELAY: f32 = 0.35;
+/// Seconds between two repeated cursor steps
+pub const CURSOR_REPEAT_RATE: f32 = 0.08;
+/// Part of the half view the grid cursor can reach before the camera follows
+pub const CURSOR_VIEW_MARGIN: f32 = 0.8;
+pub const CURSOR_COLOR: Color = Color::srgb(0.3, 0.8, 1.0);
+
+// Minimap
+pub const MINIMAP_SIZE: f32 = 150.0;
+/// Seconds between two repaints of the minimap image
+pub const MINIMAP_REFRESH: f32 = 0.2;
+
+// Combat
+pub const PROJECTILE_SPEED: f32 = 15.0;
+pub const SHELL_SPEED: f32 = 6.0;
+pub const PROJECTILE_Z: f32 = 5.0;
+pub const UNIT_Z: f32 = 4.0;
+pub const BUILDING_Z: f32 = 1.0;
+/// Screen size of the diamond a tile is drawn as in the isometric view
+pub const ISO_TILE_SIZE: Vec2 = Vec2::new(SQRT_2, FRAC_1_SQRT_2);
+/// Z offset per tile of depth in the isometric view
+pub const ISO_DEPTH_STEP: f32 = 0.001;
+/// Extra path cost of walking through a wall tile instead of around it
+pub const WALL_PATH_COST: u32 = 12;
 
+// Widgets
+pub const DISABLED_COLOR: Color = Color::srgb(0.1, 0.1, 0.1);
+pub const DISABLED_TEXT_COLOR: Color = Color::srgb(0.45, 0.45, 0.45);
+pub const OVERLAY_COLOR: Color = Color::linear_rgba(0.0, 0.0, 0.0, 0.6);
+pub const TRACK_COLOR: Color = Color::srgb(0.2, 0.2, 0.2);
+pub const ACCENT_COLOR: Color = Color::srgb(0.3, 0.7, 0.3);
+pub const FRAME_COLOR: Color = Color::srgb(0.25, 0.25, 0.25);
+pub const TITLE_FONT_SIZE: f32 = 80.0;
+pub const HEADING_FONT_SIZE: f32 = 40.0;
+pub const BODY_FONT_SIZE: f32 = 20.0;
+/// Pixels scrolled per line of mouse wheel
+pub const SCROLL_LINE_HEIGHT: f32 = 30.0;
+/// Tooltip offset from the cursor, in pixels
+pub const TOOLTIP_OFFSET: Vec2 = Vec2::new(14.0, 18.0);
+pub const TOOLTIP_Z: i32 = 10;
 pub const BUTTON_WIDTH: Val = Val::Px(120.0);
 pub const BUTTON_HEIGHT: Val = Val::Px(50.0);
 pub const PANEL_BG_COLOR: Color = Color::srgba(0.1, 0.1, 0.1, 0.8);
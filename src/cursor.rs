@@ -0,0 +1,111 @@
+//! Grid cursor, lets a gamepad point at tiles the way the mouse does
+use crate::prelude::*;
+use bevy::{prelude::*, window::CursorMoved};
+
+/// Walks the cursor over the tiles with the cursor actions, repeating while
+/// held, and clicks on its tile with [`InputAction::CursorSelect`]. The
+/// camera follows when the cursor gets close to the edge of the view
+#[allow(clippy::too_many_arguments)]
+pub fn move_grid_cursor(
+    actions: Res<ActionState>,
+    time: Res<Time>,
+    mut cursor: ResMut<GridCursor>,
+    mut held: Local<f32>,
+    mut mouse_moved: EventReader<CursorMoved>,
+    map_query: Query<&TileMap>,
+    mut camera_query: Query<(&OrthographicProjection, &mut GameCamera)>,
+    windows: Query<&Window>,
+    view_mode: Res<ViewMode>,
+    mut clicks: EventWriter<WorldClick>,
+) {
+    // The mouse takes over again as soon as it moves
+    if mouse_moved.read().count() > 0 {
+        cursor.tile = None;
+    }
+    let Ok(map) = map_query.get_single() else {
+        return;
+    };
+    let window = windows.single();
+    let (projection, mut camera) = camera_query.single_mut();
+
+    let axis = |negative, positive| {
+        actions.pressed(positive) as i32 as f32 - actions.pressed(negative) as i32 as f32
+    };
+    let direction = Vec2::new(
+        axis(InputAction::CursorLeft, InputAction::CursorRight),
+        axis(InputAction::CursorDown, InputAction::CursorUp),
+    );
+    let first = [
+        InputAction::CursorUp,
+        InputAction::CursorDown,
+        InputAction::CursorLeft,
+        InputAction::CursorRight,
+    ]
+    .into_iter()
+    .any(|action| actions.just_pressed(action));
+
+    let mut step = IVec2::ZERO;
+    if direction == Vec2::ZERO {
+        *held = 0.0;
+    } else {
+        *held += time.delta_secs();
+        if first || *held >= CURSOR_REPEAT_DELAY {
+            if !first {
+                *held -= CURSOR_REPEAT_RATE;
+            }
+            // Up on the screen, which is diagonal on the grid in the
+            // isometric view
+            step = view_mode
+                .unproject(direction)
+                .normalize_or_zero()
+                .round()
+                .as_ivec2();
+        }
+    }
+
+    let select = actions.just_pressed(InputAction::CursorSelect);
+    if step == IVec2::ZERO && !select {
+        return;
+    }
+    let Some(tile) = cursor.tile else {
+        // The first press only brings the cursor up, in the middle of the view
+        cursor.tile = Some(view_mode.unproject(camera.target).floor().as_ivec2());
+        return;
+    };
+    let max = IVec2::new(map.width as i32 - 1, map.height as i32 - 1);
+    let tile = (tile + step).clamp(IVec2::ZERO, max);
+    cursor.tile = Some(tile);
+    let center = tile.as_vec2() + Vec2::splat(0.5);
+
+    if step != IVec2::ZERO {
+        let reach = window.size() / 2.0 * projection.scale * CURSOR_VIEW_MARGIN;
+        let offset = view_mode.project(center) - camera.target;
+        camera.target += offset - offset.clamp(-reach, reach);
+        camera.zoom_anchor = None;
+    }
+    if select {
+        clicks.send(WorldClick { position: center });
+    }
+}
+
+/// Outlines the tile under the cursor, or the footprint of the building about
+/// to be placed in the editor
+pub fn draw_grid_cursor(
+    mut gizmos: Gizmos,
+    cursor: Res<GridCursor>,
+    state: Res<State<GameState>>,
+    editor_state: Option<Res<EditorState>>,
+    view_mode: Res<ViewMode>,
+) {
+    let Some(tile) = cursor.tile else {
+        return;
+    };
+    let size = match editor_state.and_then(|editor_state| editor_state.selected_building) {
+        Some(building_type) if *state.get() == GameState::LevelEditor => {
+            let (width, height) = to_size(building_type);
+            Vec2::new(width as f32, height as f32)
+        }
+        _ => Vec2::ONE,
+    };
+    draw_footprint(&mut gizmos, *view_mode, tile.as_vec2(), size, CURSOR_COLOR);
+}
@@ -0,0 +1,222 @@
+//! Health bars, damage numbers, hit flashes and rubble
+use crate::prelude::*;
+use bevy::prelude::*;
+
+type TrackedBuilding<'a> = (
+    Entity,
+    &'a Building,
+    &'a GridSize,
+    &'a Transform,
+    Option<&'a mut HealthTracker>,
+);
+type TrackedUnit<'a> = (
+    Entity,
+    &'a Unit,
+    &'a Transform,
+    Option<&'a mut HealthTracker>,
+);
+
+/// Turns health lost since the last frame into a flash, a health bar and a
+/// damage number
+pub fn track_damage(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut building_query: Query<TrackedBuilding, Without<Unit>>,
+    mut unit_query: Query<TrackedUnit, Without<Building>>,
+) {
+    let delta_secs = time.delta_secs();
+    for (entity, building, size, transform, tracker) in building_query.iter_mut() {
+        // Bars sit just above the footprint
+        let bar = Vec2::new(size.width as f32 * 0.8, size.height as f32 / 2.0 + 0.3);
+        let health = (building.health, building.max_health);
+        track(
+            &mut commands,
+            entity,
+            transform,
+            health,
+            bar,
+            tracker,
+            delta_secs,
+        );
+    }
+    for (entity, unit, transform, tracker) in unit_query.iter_mut() {
+        let bar = Vec2::new(UNIT_SIZE * 2.0, UNIT_SIZE + 0.3);
+        let health = (unit.health, unit.max_health);
+        track(
+            &mut commands,
+            entity,
+            transform,
+            health,
+            bar,
+            tracker,
+            delta_secs,
+        );
+    }
+}
+
+/// `bar` is the bar width and its height above the entity
+fn track(
+    commands: &mut Commands,
+    entity: Entity,
+    transform: &Transform,
+    (health, max_health): (f32, f32),
+    bar: Vec2,
+    tracker: Option<Mut<HealthTracker>>,
+    delta_secs: f32,
+) {
+    let Some(mut tracker) = tracker else {
+        commands
+            .entity(entity)
+            .insert(HealthTracker::new(health, max_health));
+        return;
+    };
+
+    let damage = tracker.last - health;
+    tracker.last = health;
+    tracker.max = max_health;
+    tracker.since_number += delta_secs;
+
+    if damage > 0.0 {
+        tracker.pending += damage;
+        tracker.flash = HIT_FLASH_DURATION;
+        if tracker.bar.is_none() {
+            tracker.bar = Some(spawn_health_bar(commands, entity, bar));
+        }
+    }
+
+    // Continuous damage like beams adds up into one number
+    if tracker.pending >= 1.0 && tracker.since_number >= DAMAGE_NUMBER_INTERVAL {
+        let position = transform.translation.truncate() + Vec2::Y * bar.y;
+        commands.spawn((
+            Text2d(format!("{:.0}", tracker.pending)),
+            TextColor(DAMAGE_NUMBER_COLOR),
+            Transform::from_translation(position.extend(DAMAGE_NUMBER_Z))
+                .with_scale(Vec3::splat(DAMAGE_NUMBER_SCALE)),
+            DamageNumber {
+                remaining: DAMAGE_NUMBER_DURATION,
+            },
+        ));
+        tracker.pending = 0.0;
+        tracker.since_number = 0.0;
+    }
+}
+
+fn spawn_health_bar(commands: &mut Commands, entity: Entity, bar: Vec2) -> (Entity, Entity) {
+    let size = Vec2::new(bar.x, HEALTH_BAR_HEIGHT);
+    let fill = commands
+        .spawn((
+            Sprite::from_color(HEALTH_BAR_COLOR, size),
+            Transform::from_xyz(0.0, 0.0, 0.1),
+            HealthBarFill { width: bar.x },
+        ))
+        .id();
+    let root = commands
+        .spawn((
+            Sprite::from_color(HEALTH_BAR_BACKGROUND, size),
+            Transform::from_xyz(0.0, bar.y, HEALTH_BAR_Z),
+        ))
+        .add_child(fill)
+        .id();
+    commands.entity(entity).add_child(root);
+    (root, fill)
+}
+
+/// Shrinks the bars with the health left, full health hides them
+pub fn update_health_bars(
+    tracker_query: Query<&HealthTracker, Changed<HealthTracker>>,
+    mut root_query: Query<&mut Visibility, Without<HealthBarFill>>,
+    mut fill_query: Query<(&mut Sprite, &mut Transform, &HealthBarFill)>,
+) {
+    for tracker in tracker_query.iter() {
+        let Some((root, fill)) = tracker.bar else {
+            continue;
+        };
+        let ratio = (tracker.last / tracker.max.max(f32::EPSILON)).clamp(0.0, 1.0);
+
+        if let Ok(mut visibility) = root_query.get_mut(root) {
+            visibility.set_if_neq(if ratio < 1.0 {
+                Visibility::Inherited
+            } else {
+                Visibility::Hidden
+            });
+        }
+        if let Ok((mut sprite, mut transform, bar)) = fill_query.get_mut(fill) {
+            // Stays aligned to the left end of the bar
+            sprite.custom_size = Some(Vec2::new(bar.width * ratio, HEALTH_BAR_HEIGHT));
+            transform.translation.x = -bar.width * (1.0 - ratio) / 2.0;
+        }
+    }
+}
+
+type FlashTarget<'a> = (
+    &'a mut HealthTracker,
+    Option<&'a mut MeshMaterial2d<ColorMaterial>>,
+    Option<&'a mut Sprite>,
+);
+
+/// Hit entities light up briefly, meshes swap to the flash material and
+/// sprites get tinted
+pub fn flash_hits(time: Res<Time>, assets: Res<FeedbackAssets>, mut query: Query<FlashTarget>) {
+    for (mut tracker, material, sprite) in query.iter_mut() {
+        if tracker.flash <= 0.0 && tracker.material.is_none() {
+            continue;
+        }
+        tracker.flash -= time.delta_secs();
+        let flashing = tracker.flash > 0.0;
+
+        if let Some(mut material) = material {
+            if flashing && tracker.material.is_none() {
+                tracker.material = Some(std::mem::replace(&mut material.0, assets.flash.clone()));
+            } else if !flashing {
+                if let Some(original) = tracker.material.take() {
+                    material.0 = original;
+                }
+            }
+        }
+        if let Some(mut sprite) = sprite {
+            sprite.color = if flashing {
+                HIT_FLASH_COLOR.with_alpha(0.6)
+            } else {
+                Color::WHITE
+            };
+        }
+    }
+}
+
+pub fn float_damage_numbers(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut Transform, &mut TextColor, &mut DamageNumber)>,
+) {
+    for (entity, mut transform, mut color, mut number) in query.iter_mut() {
+        number.remaining -= time.delta_secs();
+        if number.remaining <= 0.0 {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        transform.translation.y += DAMAGE_NUMBER_SPEED * time.delta_secs();
+        color.0 = color
+            .0
+            .with_alpha(number.remaining / DAMAGE_NUMBER_DURATION);
+    }
+}
+
+/// Leaves rubble where a building was destroyed
+pub fn spawn_rubble(
+    mut commands: Commands,
+    mut events: EventReader<BuildingDestroyed>,
+    assets: Res<FeedbackAssets>,
+    query: Query<(&Transform, &GridSize)>,
+) {
+    for event in events.read() {
+        let Ok((transform, size)) = query.get(event.entity) else {
+            continue;
+        };
+        commands.spawn((
+            Transform::from_translation(transform.translation.truncate().extend(RUBBLE_Z))
+                .with_scale(Vec3::new(size.width as f32, size.height as f32, 1.0)),
+            assets.rubble.to_component(),
+            Rubble,
+        ));
+    }
+}
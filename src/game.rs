@@ -1,10 +1,20 @@
 use crate::prelude::*;
-use bevy::{prelude::*, utils::HashMap};
+use bevy::{
+    asset::LoadState,
+    prelude::*,
+    render::{
+        mesh::{Indices, PrimitiveTopology},
+        render_asset::RenderAssetUsages,
+    },
+    utils::HashMap,
+};
 
 pub fn setup_game(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
+    asset_server: Res<AssetServer>,
+    start: Res<SessionStart>,
 ) {
     info!("Setup game core");
     let map = TileMap::new(100, 100);
@@ -13,8 +23,14 @@ pub fn setup_game(
     let mut resources = HashMap::new();
     resources.insert(ResourceType::Gold, 1000.0);
     resources.insert(ResourceType::Elixir, 1000.0);
+    if let SessionStart::Continue(save) = &*start {
+        resources.extend(save.resources.iter().copied());
+    }
 
     commands.insert_resource(PlayerResources { resources });
+    commands.insert_resource(SpellInventory::default());
+    commands.insert_resource(Clan::default());
+    commands.insert_resource(GridCursor::default());
 
     let townhall_square = meshes.add(Rectangle::new(TOWNHALL_SIZE.x, TOWNHALL_SIZE.y));
     let townhall_color = materials.add(TOWNHALL_COLOR);
@@ -24,8 +40,19 @@ pub fn setup_game(
     let storage_square = meshes.add(Rectangle::new(STORAGE_SIZE.x, STORAGE_SIZE.y));
     let defense_square = meshes.add(Rectangle::new(DEFENSE_SIZE.x, DEFENSE_SIZE.y));
     let defense_color = materials.add(DEFENSE_COLOR);
+    let mortar_color = materials.add(MORTAR_COLOR);
+    let air_defense_color = materials.add(AIR_DEFENSE_COLOR);
+    let inferno_square = meshes.add(Rectangle::new(INFERNO_SIZE.x, INFERNO_SIZE.y));
+    let inferno_color = materials.add(INFERNO_COLOR);
     let wall_square = meshes.add(Rectangle::new(WALL_SIZE.x, WALL_SIZE.y));
     let wall_color = materials.add(WALL_COLOR);
+    let bomb_color = materials.add(BOMB_COLOR);
+    let spring_trap_color = materials.add(SPRING_TRAP_COLOR);
+    let air_mine_color = materials.add(AIR_MINE_COLOR);
+    let spell_factory_color = materials.add(SPELL_FACTORY_COLOR);
+    let king_color = materials.add(KING_COLOR);
+    let queen_color = materials.add(QUEEN_COLOR);
+    let clan_castle_color = materials.add(CLAN_CASTLE_COLOR);
     let default_color = materials.add(WHITE);
 
     let townhall = Handles::new(&townhall_square, &townhall_color);
@@ -34,7 +61,14 @@ pub fn setup_game(
     let elixir_storage = Handles::new(&storage_square, &elixir_color);
     let gold_storage = Handles::new(&storage_square, &gold_color);
     let defense = Handles::new(&defense_square, &defense_color);
+    let mortar = Handles::new(&defense_square, &mortar_color);
+    let air_defense = Handles::new(&defense_square, &air_defense_color);
+    let inferno = Handles::new(&inferno_square, &inferno_color);
     let wall = Handles::new(&wall_square, &wall_color);
+    let bomb = Handles::new(&wall_square, &bomb_color);
+    let spring_trap = Handles::new(&wall_square, &spring_trap_color);
+    let air_mine = Handles::new(&wall_square, &air_mine_color);
+    let spell_factory = Handles::new(&defense_square, &spell_factory_color);
 
     let mut assets = BuildingAssets::new(Handles::new(&wall_square, &default_color));
     assets.insert(BuildingType::TownHall, townhall);
@@ -45,13 +79,112 @@ pub fn setup_game(
     assets.insert(BuildingType::Collector(ResourceType::Gold), gold_collector);
     assets.insert(BuildingType::Storage(ResourceType::Elixir), elixir_storage);
     assets.insert(BuildingType::Storage(ResourceType::Gold), gold_storage);
-    assets.insert(BuildingType::Defense, defense);
+    assets.insert(BuildingType::Defense(DefenseKind::Tower), defense);
+    assets.insert(BuildingType::Defense(DefenseKind::Mortar), mortar);
+    assets.insert(BuildingType::Defense(DefenseKind::AirDefense), air_defense);
+    assets.insert(BuildingType::Defense(DefenseKind::Inferno), inferno);
     assets.insert(BuildingType::Wall, wall);
+    assets.insert(BuildingType::Trap(TrapKind::Bomb), bomb);
+    assets.insert(BuildingType::Trap(TrapKind::SpringTrap), spring_trap);
+    assets.insert(BuildingType::Trap(TrapKind::AirMine), air_mine);
+    assets.insert(BuildingType::SpellFactory, spell_factory);
+    assets.insert(
+        BuildingType::HeroAltar(HeroType::King),
+        Handles::new(&defense_square, &king_color),
+    );
+    assets.insert(
+        BuildingType::HeroAltar(HeroType::Queen),
+        Handles::new(&defense_square, &queen_color),
+    );
+    assets.insert(
+        BuildingType::ClanCastle,
+        Handles::new(&defense_square, &clan_castle_color),
+    );
 
+    // Without art the buildings stay colored rectangles
+    commands.insert_resource(PendingBuildingAtlas(asset_server.load(BUILDING_ATLAS_PATH)));
     commands.insert_resource(assets);
+
+    commands.insert_resource(FeedbackAssets {
+        flash: materials.add(HIT_FLASH_COLOR),
+        rubble: Handles::new(
+            &meshes.add(Rectangle::new(1.0, 1.0)),
+            &materials.add(RUBBLE_COLOR),
+        ),
+    });
+
+    let projectile_circle = meshes.add(Circle::new(PROJECTILE_SIZE));
+    let projectile_color = materials.add(PROJECTILE_COLOR);
+    commands.insert_resource(ProjectileAssets(Handles::new(
+        &projectile_circle,
+        &projectile_color,
+    )));
+
+    let unit_circle = meshes.add(Circle::new(UNIT_SIZE));
+    let barbarian_color = materials.add(BARBARIAN_COLOR);
+    let wall_breaker_color = materials.add(WALL_BREAKER_COLOR);
+    let minion_color = materials.add(MINION_COLOR);
+
+    let mut unit_assets = UnitAssets::new(Handles::new(&unit_circle, &default_color));
+    unit_assets.insert(
+        UnitType::Barbarian,
+        Handles::new(&unit_circle, &barbarian_color),
+    );
+    unit_assets.insert(
+        UnitType::WallBreaker,
+        Handles::new(&unit_circle, &wall_breaker_color),
+    );
+    unit_assets.insert(UnitType::Minion, Handles::new(&unit_circle, &minion_color));
+    let hero_circle = meshes.add(Circle::new(HERO_SIZE));
+    unit_assets.insert(
+        UnitType::Hero(HeroType::King),
+        Handles::new(&hero_circle, &king_color),
+    );
+    unit_assets.insert(
+        UnitType::Hero(HeroType::Queen),
+        Handles::new(&hero_circle, &queen_color),
+    );
+    commands.insert_resource(unit_assets);
+
+    // Zones are unit circles scaled to the spell radius
+    let spell_circle = meshes.add(Circle::new(1.0));
+    let mut spell_assets = SpellAssets::default();
+    for (spell, color) in [
+        (SpellType::Lightning, LIGHTNING_COLOR),
+        (SpellType::Heal, HEAL_COLOR),
+        (SpellType::Rage, RAGE_COLOR),
+        (SpellType::Freeze, FREEZE_COLOR),
+    ] {
+        spell_assets.insert(spell, Handles::new(&spell_circle, &materials.add(color)));
+    }
+    commands.insert_resource(spell_assets);
 }
 
-pub fn spawn_initial_buildings(mut commands: Commands, assets: Res<BuildingAssets>) {
+pub fn spawn_initial_buildings(
+    mut commands: Commands,
+    assets: Res<BuildingAssets>,
+    start: Res<SessionStart>,
+) {
+    match &*start {
+        SessionStart::NewGame => (),
+        SessionStart::Continue(save) => {
+            info!("Loading {} buildings", save.buildings.len());
+            for saved in &save.buildings {
+                let entity = place_building(
+                    &mut commands,
+                    &assets,
+                    saved.building_type,
+                    saved.level,
+                    saved.x,
+                    saved.y,
+                );
+                saved.restore(&mut commands.entity(entity));
+            }
+            return;
+        }
+        SessionStart::Sandbox => return,
+    }
+
     info!("Spawning buildings");
 
     town_hall(&mut commands, &assets, 1, 45, 45);
@@ -60,6 +193,9 @@ pub fn spawn_initial_buildings(mut commands: Commands, assets: Res<BuildingAsset
     gold_storage(&mut commands, &assets, 1, 40, 50);
     elixir_storage(&mut commands, &assets, 1, 50, 50);
     defense_tower(&mut commands, &assets, 1, 45, 55);
+    spell_factory(&mut commands, &assets, 1, 55, 45);
+    king_altar(&mut commands, &assets, 1, 55, 50);
+    clan_castle(&mut commands, &assets, 1, 35, 45);
 
     // Spawn some walls for perimeter protection
     for i in 0..5 {
@@ -70,54 +206,91 @@ pub fn spawn_initial_buildings(mut commands: Commands, assets: Res<BuildingAsset
     }
 }
 
-pub fn collect_resources(
-    time: Res<Time>,
-    mut resources: ResMut<PlayerResources>,
-    query: Query<&ResourceCollector>,
-) {
-    trace!("Collecting resources {resources:?}");
-    for resource_collector in query.iter() {
+/// Collectors fill up to their capacity over time. Nothing reaches the player
+/// resources on its own anymore, the stored amount has to be collected from
+/// the info panel
+pub fn collect_resources(time: Res<Time>, mut query: Query<&mut ResourceCollector>) {
+    for mut resource_collector in query.iter_mut() {
         let amount = resource_collector.production_rate * time.delta_secs();
-        *resources
-            .resources
-            .entry(resource_collector.resource_type)
-            .or_insert(0.0) += amount as f64;
+        resource_collector.stored =
+            (resource_collector.stored + amount).min(resource_collector.capacity);
+    }
+}
+
+/// Every grid line as a thin quad of one mesh, `line_width` thick
+fn grid_mesh(width: usize, height: usize, line_width: f32) -> Mesh {
+    let half = line_width / 2.0;
+    let (size_x, size_y) = (width as f32, height as f32);
+    let mut positions = Vec::new();
+    let mut indices = Vec::new();
+    let mut push_quad = |min: Vec2, max: Vec2| {
+        let start = positions.len() as u32;
+        positions.extend([
+            [min.x, min.y, 0.0],
+            [max.x, min.y, 0.0],
+            [max.x, max.y, 0.0],
+            [min.x, max.y, 0.0],
+        ]);
+        indices.extend([start, start + 1, start + 2, start, start + 2, start + 3]);
+    };
+
+    for y in 0..=height {
+        let y = y as f32;
+        push_quad(Vec2::new(0.0, y - half), Vec2::new(size_x, y + half));
     }
+    for x in 0..=width {
+        let x = x as f32;
+        push_quad(Vec2::new(x - half, 0.0), Vec2::new(x + half, size_y));
+    }
+
+    let vertex_count = positions.len();
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 0.0, 1.0]; vertex_count])
+    .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0, 0.0]; vertex_count])
+    .with_inserted_indices(Indices::U32(indices))
 }
 
 pub fn setup_grid(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
+    map_query: Query<&TileMap>,
+    settings: Res<Settings>,
 ) {
-    let grid_size = 100.0;
-    let line_width = 0.1;
-    info!("Setup map grid with len: {grid_size}, wid: {line_width}");
-    let grid_line_material = materials.add(Color::srgba(0.5, 0.5, 0.5, 0.3));
-
-    // Create horizontal and vertical lines
-    for i in 0..=100 {
-        // Horizontal lines
-        commands.spawn((
-            Mesh2d(meshes.add(Rectangle::from_size(Vec2::new(grid_size, line_width)))),
-            MeshMaterial2d(grid_line_material.clone()),
-            Transform::from_xyz(50.0, i as f32, 0.0),
-            GridVisual,
-        ));
-
-        // Vertical lines
-        commands.spawn((
-            Mesh2d(meshes.add(Rectangle::from_size(Vec2::new(line_width, grid_size)))),
-            MeshMaterial2d(grid_line_material.clone()),
-            Transform::from_xyz(i as f32, 50.0, 0.0),
-            GridVisual,
-        ));
+    let map = map_query.single();
+    info!(
+        "Setup map grid of {}x{}, wid: {GRID_LINE_WIDTH}",
+        map.width, map.height
+    );
+
+    commands.spawn((
+        Mesh2d(meshes.add(grid_mesh(map.width, map.height, GRID_LINE_WIDTH))),
+        MeshMaterial2d(materials.add(GRID_COLOR)),
+        Transform::default(),
+        if settings.show_grid {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        },
+        GridVisual,
+    ));
+}
+
+/// Shows or hides the grid, and remembers it in the settings
+pub fn toggle_grid(actions: Res<ActionState>, mut settings: ResMut<Settings>) {
+    if actions.just_pressed(InputAction::ToggleGrid) {
+        settings.show_grid = !settings.show_grid;
     }
 }
 
+type PlacedFilter = Or<(Added<Building>, Added<Trap>)>;
 pub fn synchronize_buildings_with_map(
     mut map_query: Query<&mut TileMap>,
-    building_query: Query<(Entity, &GridPosition, &GridSize), Added<Building>>,
+    building_query: Query<(Entity, &GridPosition, &GridSize), PlacedFilter>,
 ) {
     if let Ok(mut tile_map) = map_query.get_single_mut() {
         for (entity, position, size) in building_query.iter() {
@@ -126,3 +299,178 @@ pub fn synchronize_buildings_with_map(
         }
     }
 }
+
+/// Gives every group of touching walls its own segment id
+pub fn recompute_wall_segments(
+    mut wall_query: Query<(Entity, &GridPosition, &mut Wall), Without<Destroyed>>,
+    added: Query<(), Added<Wall>>,
+    mut destroyed: EventReader<BuildingDestroyed>,
+    mut restored: RemovedComponents<Destroyed>,
+) {
+    let wall_fell = destroyed
+        .read()
+        .any(|event| event.building_type == BuildingType::Wall);
+    let wall_restored = restored.read().count() > 0;
+    if added.is_empty() && !wall_fell && !wall_restored {
+        return;
+    }
+
+    let walls: HashMap<(usize, usize), Entity> = wall_query
+        .iter()
+        .map(|(entity, position, _)| ((position.x, position.y), entity))
+        .collect();
+    let mut segments: HashMap<Entity, usize> = HashMap::new();
+    let mut segment = 0;
+
+    for (&start, &entity) in &walls {
+        if segments.contains_key(&entity) {
+            continue;
+        }
+        let mut stack = vec![start];
+        segments.insert(entity, segment);
+        while let Some((x, y)) = stack.pop() {
+            let neighbours = [
+                (x + 1, y),
+                (x.wrapping_sub(1), y),
+                (x, y + 1),
+                (x, y.wrapping_sub(1)),
+            ];
+            for position in neighbours {
+                if let Some(&neighbour) = walls.get(&position) {
+                    if !segments.contains_key(&neighbour) {
+                        segments.insert(neighbour, segment);
+                        stack.push(position);
+                    }
+                }
+            }
+        }
+        segment += 1;
+    }
+
+    debug!("Recomputed {segment} wall segments");
+    for (entity, _, mut wall) in wall_query.iter_mut() {
+        wall.segment = segments[&entity];
+    }
+}
+
+type BattleBuilding<'a> = (
+    Entity,
+    &'a mut Building,
+    &'a GridPosition,
+    &'a GridSize,
+    Has<Destroyed>,
+);
+type BattleEntity = Or<(
+    With<Unit>,
+    With<Projectile>,
+    With<SpellZone>,
+    With<Rubble>,
+    With<DamageNumber>,
+)>;
+
+/// Brings the village back to how it was before the battle
+pub fn end_battle(
+    mut commands: Commands,
+    mut map_query: Query<&mut TileMap>,
+    mut building_query: Query<BattleBuilding>,
+    unit_query: Query<Entity, BattleEntity>,
+) {
+    info!("Ending battle");
+    for entity in unit_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let Ok(mut map) = map_query.get_single_mut() else {
+        return;
+    };
+    for (entity, mut building, position, size, destroyed) in building_query.iter_mut() {
+        building.health = building.max_health;
+        if !destroyed {
+            continue;
+        }
+        map.place(position.x, position.y, entity, (size.width, size.height));
+        commands
+            .entity(entity)
+            .remove::<Destroyed>()
+            .insert(Visibility::Inherited);
+    }
+}
+
+/// Builds the building atlas once its art is loaded. When it fails to load
+/// the buildings keep their meshes
+pub fn load_building_atlas(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    pending: Option<Res<PendingBuildingAtlas>>,
+    assets: Option<ResMut<BuildingAssets>>,
+    mut layouts: ResMut<Assets<TextureAtlasLayout>>,
+) {
+    let (Some(pending), Some(mut assets)) = (pending, assets) else {
+        return;
+    };
+    match asset_server.load_state(&pending.0) {
+        LoadState::Loaded => {
+            let layout = TextureAtlasLayout::from_grid(
+                UVec2::splat(BUILDING_SPRITE_SIZE),
+                BUILDING_ATLAS_LEVELS,
+                BUILDING_ATLAS_ROWS,
+                None,
+                None,
+            );
+            assets.atlas = Some(BuildingAtlas::from_rows(
+                pending.0.clone(),
+                layouts.add(layout),
+                BUILDING_ATLAS_LEVELS,
+            ));
+            info!("Using building art from {BUILDING_ATLAS_PATH}");
+        }
+        LoadState::Failed(error) => {
+            info!("No building art, keeping the colored meshes: {error}");
+        }
+        LoadState::NotLoaded | LoadState::Loading => return,
+    }
+    commands.remove_resource::<PendingBuildingAtlas>();
+}
+
+type SpriteTarget<'a> = (
+    Entity,
+    Ref<'a, BuildingType>,
+    &'a GridSize,
+    Option<Ref<'a, Building>>,
+    Option<&'a Sprite>,
+);
+
+/// Swaps building meshes for atlas sprites, and picks the art of the new
+/// level after an upgrade
+pub fn apply_building_sprites(
+    mut commands: Commands,
+    assets: Option<Res<BuildingAssets>>,
+    query: Query<SpriteTarget>,
+) {
+    let Some(assets) = assets.filter(|assets| assets.atlas.is_some()) else {
+        return;
+    };
+
+    for (entity, building_type, size, building, current) in query.iter() {
+        // The art loads in after the first buildings are placed
+        let changed = building_type.is_added() || building.as_ref().is_some_and(Ref::is_changed);
+        if !assets.is_changed() && !changed {
+            continue;
+        }
+        let level = building.map_or(1, |building| building.level);
+        let size = Vec2::new(size.width as f32, size.height as f32);
+        let Some(sprite) = assets.sprite(&building_type, level, size) else {
+            continue;
+        };
+
+        // Health changes trigger this too, nothing to do if the art is the same
+        let index = |sprite: &Sprite| sprite.texture_atlas.as_ref().map(|atlas| atlas.index);
+        if current.is_some_and(|current| index(current) == index(&sprite)) {
+            continue;
+        }
+        commands
+            .entity(entity)
+            .remove::<(Mesh2d, MeshMaterial2d<ColorMaterial>)>()
+            .insert(sprite);
+    }
+}
@@ -0,0 +1,154 @@
+//! Hero altars: regeneration, guarding the village, deployment and abilities
+use crate::prelude::*;
+use bevy::prelude::*;
+
+/// Heroes resting in their altar heal over real time, even while paused
+pub fn regenerate_heroes(time: Res<Time<Real>>, mut query: Query<&mut HeroAltar>) {
+    for mut altar in query.iter_mut() {
+        if altar.deployed.is_some() {
+            continue;
+        }
+        let max_health = altar.max_health();
+        if altar.health < max_health {
+            altar.health =
+                (altar.health + HERO_REGEN_PER_SECOND * time.delta_secs()).min(max_health);
+        }
+    }
+}
+
+/// Sends every fully healed hero out to guard its altar
+pub fn post_hero_guards(
+    mut commands: Commands,
+    assets: Res<UnitAssets>,
+    mut query: Query<(Entity, &Transform, &mut HeroAltar), Without<Destroyed>>,
+) {
+    for (entity, transform, mut altar) in query.iter_mut() {
+        if !altar.is_ready() {
+            continue;
+        }
+
+        let post = transform.translation.truncate();
+        let guard = hero(
+            &mut commands,
+            &assets,
+            altar.hero,
+            altar.level,
+            altar.health,
+            post,
+            Some(entity),
+        );
+        commands
+            .entity(guard)
+            .insert(Defender::new(post, HERO_GUARD_RADIUS));
+        altar.deployed = Some(guard);
+        debug!("{:?} is guarding {entity:?}", altar.hero);
+    }
+}
+
+/// Hurt guards go back to rest once no attacker is around
+pub fn recall_hero_guards(
+    mut commands: Commands,
+    guard_query: Query<(Entity, &Unit, &Defender), With<Hero>>,
+    attacker_query: Query<&Transform, (With<Unit>, Without<Defender>)>,
+) {
+    for (entity, unit, defender) in guard_query.iter() {
+        let threatened = attacker_query.iter().any(|transform| {
+            defender.post.distance(transform.translation.truncate()) <= defender.guard_radius
+        });
+        if unit.health < unit.max_health && !threatened {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// Guards stay home while their hero is out attacking
+pub fn dismiss_hero_guards(
+    mut commands: Commands,
+    query: Query<Entity, (With<Hero>, With<Defender>)>,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Stores the hero health in its altar, so it survives the unit
+pub fn sync_hero_health(hero_query: Query<(&Hero, &Unit)>, mut altar_query: Query<&mut HeroAltar>) {
+    for (hero, unit) in hero_query.iter() {
+        let Some(mut altar) = hero.altar.and_then(|altar| altar_query.get_mut(altar).ok()) else {
+            continue;
+        };
+        altar.health = unit.health.max(0.0);
+    }
+}
+
+/// Frees the altar once its hero died or left the battlefield
+pub fn return_heroes(mut removed: RemovedComponents<Hero>, mut altar_query: Query<&mut HeroAltar>) {
+    for entity in removed.read() {
+        for mut altar in altar_query.iter_mut() {
+            if altar.deployed == Some(entity) {
+                altar.deployed = None;
+            }
+        }
+    }
+}
+
+pub fn tick_hero_abilities(time: Res<Time>, mut query: Query<&mut Hero>) {
+    for mut hero in query.iter_mut() {
+        hero.ability_cooldown = (hero.ability_cooldown - time.delta_secs()).max(0.0);
+    }
+}
+
+/// Uses the ability of the attacking hero, false if it isn't out or ready
+pub fn activate_hero_ability(
+    commands: &mut Commands,
+    hero_type: HeroType,
+    query: &mut Query<(Entity, &mut Hero), Without<Defender>>,
+) -> bool {
+    let Some((entity, mut hero)) = query
+        .iter_mut()
+        .find(|(_, hero)| hero.hero_type == hero_type && hero.ability_cooldown <= 0.0)
+    else {
+        return false;
+    };
+
+    hero.ability_cooldown = HERO_ABILITY_COOLDOWN;
+    match hero_type {
+        HeroType::King => commands.entity(entity).try_insert(Raged {
+            damage_multiplier: RAGE_DAMAGE_MULTIPLIER,
+            speed_multiplier: RAGE_SPEED_MULTIPLIER,
+            remaining: HERO_ABILITY_DURATION,
+        }),
+        HeroType::Queen => commands.entity(entity).try_insert(Healing {
+            per_second: QUEEN_ABILITY_HEAL_PER_SECOND,
+            remaining: HERO_ABILITY_DURATION,
+        }),
+    };
+    true
+}
+
+/// Levels the hero up, it has to regenerate the extra health before
+/// being ready again
+pub fn upgrade_hero(
+    hero_type: HeroType,
+    resources: &mut PlayerResources,
+    altars: &mut Query<&mut HeroAltar>,
+) -> bool {
+    let Some(mut altar) = altars.iter_mut().find(|altar| altar.hero == hero_type) else {
+        return false;
+    };
+    if altar.level >= HERO_UPGRADE_COST.len() as u32 {
+        return false;
+    }
+
+    let cost = HERO_UPGRADE_COST[validate_level(altar.level)];
+    let elixir = resources
+        .resources
+        .entry(ResourceType::Elixir)
+        .or_insert(0.0);
+    if *elixir < cost {
+        return false;
+    }
+    *elixir -= cost;
+    altar.level += 1;
+    true
+}
@@ -0,0 +1,370 @@
+//! Input actions, the game reads what the player wants to do instead of which
+//! key or button was pressed. Bindings come from the [`Settings`]
+use crate::prelude::*;
+use bevy::{
+    input::{mouse::MouseWheel, touch::Touches},
+    prelude::*,
+    utils::HashSet,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum InputAction {
+    /// Pointer button, drags the map or clicks on it
+    Place,
+    Cancel,
+    Undo,
+    /// Switches the camera between the top-down and isometric views
+    Rotate,
+    ToggleDebug,
+    ToggleGrid,
+    Pause,
+    PanUp,
+    PanDown,
+    PanLeft,
+    PanRight,
+    ZoomIn,
+    ZoomOut,
+    /// Moves the grid cursor one tile
+    CursorUp,
+    CursorDown,
+    CursorLeft,
+    CursorRight,
+    /// Clicks on the tile under the grid cursor
+    CursorSelect,
+    /// Picks the next building to place in the editor
+    CycleBuilding,
+}
+
+impl InputAction {
+    pub const ALL: [InputAction; 19] = [
+        InputAction::Place,
+        InputAction::Cancel,
+        InputAction::Undo,
+        InputAction::Rotate,
+        InputAction::ToggleDebug,
+        InputAction::ToggleGrid,
+        InputAction::Pause,
+        InputAction::PanUp,
+        InputAction::PanDown,
+        InputAction::PanLeft,
+        InputAction::PanRight,
+        InputAction::ZoomIn,
+        InputAction::ZoomOut,
+        InputAction::CursorUp,
+        InputAction::CursorDown,
+        InputAction::CursorLeft,
+        InputAction::CursorRight,
+        InputAction::CursorSelect,
+        InputAction::CycleBuilding,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            InputAction::Place => "Place",
+            InputAction::Cancel => "Cancel",
+            InputAction::Undo => "Undo",
+            InputAction::Rotate => "Rotate View",
+            InputAction::ToggleDebug => "Debug Overlay",
+            InputAction::ToggleGrid => "Grid",
+            InputAction::Pause => "Pause",
+            InputAction::PanUp => "Pan Up",
+            InputAction::PanDown => "Pan Down",
+            InputAction::PanLeft => "Pan Left",
+            InputAction::PanRight => "Pan Right",
+            InputAction::ZoomIn => "Zoom In",
+            InputAction::ZoomOut => "Zoom Out",
+            InputAction::CursorUp => "Cursor Up",
+            InputAction::CursorDown => "Cursor Down",
+            InputAction::CursorLeft => "Cursor Left",
+            InputAction::CursorRight => "Cursor Right",
+            InputAction::CursorSelect => "Cursor Select",
+            InputAction::CycleBuilding => "Next Building",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Binding {
+    Key(KeyCode),
+    /// Modifier held while the key is pressed
+    Chord(KeyCode, KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButton),
+}
+
+impl Binding {
+    pub fn is_keyboard(&self) -> bool {
+        matches!(self, Binding::Key(_) | Binding::Chord(..))
+    }
+
+    pub fn label(&self) -> String {
+        let key = |key: &KeyCode| format!("{key:?}").replace("Key", "").replace("Digit", "");
+        match self {
+            Binding::Key(code) => key(code),
+            Binding::Chord(modifier, code) => format!("{}+{}", key(modifier), key(code)),
+            Binding::Mouse(button) => format!("Mouse {button:?}"),
+            Binding::Gamepad(button) => format!("Pad {button:?}"),
+        }
+    }
+
+    /// Pressed, just pressed and just released
+    fn state(
+        &self,
+        keyboard: &ButtonInput<KeyCode>,
+        mouse: &ButtonInput<MouseButton>,
+        gamepads: &Query<&Gamepad>,
+    ) -> (bool, bool, bool) {
+        match self {
+            Binding::Key(code) => (
+                keyboard.pressed(*code),
+                keyboard.just_pressed(*code),
+                keyboard.just_released(*code),
+            ),
+            Binding::Chord(modifier, code) => {
+                let held = keyboard.pressed(*modifier);
+                (
+                    held && keyboard.pressed(*code),
+                    held && keyboard.just_pressed(*code),
+                    keyboard.just_released(*code),
+                )
+            }
+            Binding::Mouse(button) => (
+                mouse.pressed(*button),
+                mouse.just_pressed(*button),
+                mouse.just_released(*button),
+            ),
+            Binding::Gamepad(button) => (
+                gamepads.iter().any(|pad| pad.pressed(*button)),
+                gamepads.iter().any(|pad| pad.just_pressed(*button)),
+                gamepads.iter().any(|pad| pad.just_released(*button)),
+            ),
+        }
+    }
+}
+
+/// Actions missing from a settings file keep their default bindings
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(transparent)]
+pub struct Bindings(pub BTreeMap<InputAction, Vec<Binding>>);
+
+impl Default for Bindings {
+    fn default() -> Self {
+        use Binding::*;
+        use InputAction::*;
+        Bindings(BTreeMap::from([
+            (Place, vec![Mouse(MouseButton::Left)]),
+            (
+                Cancel,
+                vec![Mouse(MouseButton::Right), Gamepad(GamepadButton::East)],
+            ),
+            (
+                Undo,
+                vec![
+                    Chord(KeyCode::ControlLeft, KeyCode::KeyZ),
+                    Key(KeyCode::Backspace),
+                    Gamepad(GamepadButton::Select),
+                ],
+            ),
+            (Rotate, vec![Key(KeyCode::F2)]),
+            (ToggleDebug, vec![Key(KeyCode::F3)]),
+            (ToggleGrid, vec![Key(KeyCode::KeyG)]),
+            (
+                Pause,
+                vec![Key(KeyCode::Escape), Gamepad(GamepadButton::Start)],
+            ),
+            (PanUp, vec![Key(KeyCode::KeyW), Key(KeyCode::ArrowUp)]),
+            (PanDown, vec![Key(KeyCode::KeyS), Key(KeyCode::ArrowDown)]),
+            (PanLeft, vec![Key(KeyCode::KeyA), Key(KeyCode::ArrowLeft)]),
+            (PanRight, vec![Key(KeyCode::KeyD), Key(KeyCode::ArrowRight)]),
+            (ZoomIn, vec![Key(KeyCode::Equal)]),
+            (ZoomOut, vec![Key(KeyCode::Minus)]),
+            (CursorUp, vec![Gamepad(GamepadButton::DPadUp)]),
+            (CursorDown, vec![Gamepad(GamepadButton::DPadDown)]),
+            (CursorLeft, vec![Gamepad(GamepadButton::DPadLeft)]),
+            (CursorRight, vec![Gamepad(GamepadButton::DPadRight)]),
+            (CursorSelect, vec![Gamepad(GamepadButton::South)]),
+            (CycleBuilding, vec![Gamepad(GamepadButton::West)]),
+        ]))
+    }
+}
+
+impl Bindings {
+    pub fn get(&self, action: InputAction) -> Vec<Binding> {
+        match self.0.get(&action) {
+            Some(bindings) => bindings.clone(),
+            None => Bindings::default().0.remove(&action).unwrap_or_default(),
+        }
+    }
+
+    /// The key replaces the keyboard bindings, mouse and gamepad ones stay
+    pub fn rebind_key(&mut self, action: InputAction, key: KeyCode) {
+        let mut bindings = self.get(action);
+        bindings.retain(|binding| !binding.is_keyboard());
+        bindings.insert(0, Binding::Key(key));
+        self.0.insert(action, bindings);
+    }
+
+    pub fn label(&self, action: InputAction) -> String {
+        let labels: Vec<_> = self.get(action).iter().map(Binding::label).collect();
+        if labels.is_empty() {
+            "-".into()
+        } else {
+            labels.join(" / ")
+        }
+    }
+}
+
+/// What the player is doing this frame, filled from the bindings before
+/// `Update` runs
+#[derive(Resource, Debug, Default)]
+pub struct ActionState {
+    pressed: HashSet<InputAction>,
+    just_pressed: HashSet<InputAction>,
+    just_released: HashSet<InputAction>,
+    /// Screen position of the mouse cursor, or of the first touch
+    pub pointer: Option<Vec2>,
+    pub pointer_is_touch: bool,
+    /// Pan direction held, each axis within -1 to 1
+    pub pan: Vec2,
+    /// Zoom held, positive zooms in
+    pub zoom_hold: f32,
+    /// Zoom steps from the mouse wheel this frame
+    pub zoom_steps: f32,
+}
+
+impl ActionState {
+    pub fn pressed(&self, action: InputAction) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    pub fn just_pressed(&self, action: InputAction) -> bool {
+        self.just_pressed.contains(&action)
+    }
+
+    pub fn just_released(&self, action: InputAction) -> bool {
+        self.just_released.contains(&action)
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn update_action_state(
+    mut actions: ResMut<ActionState>,
+    settings: Res<Settings>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    touches: Res<Touches>,
+    gamepads: Query<&Gamepad>,
+    mut wheel: EventReader<MouseWheel>,
+    windows: Query<&Window>,
+) {
+    actions.pressed.clear();
+    actions.just_pressed.clear();
+    actions.just_released.clear();
+
+    for action in InputAction::ALL {
+        for binding in settings.bindings.get(action) {
+            let (pressed, just_pressed, just_released) =
+                binding.state(&keyboard, &mouse, &gamepads);
+            if pressed {
+                actions.pressed.insert(action);
+            }
+            if just_pressed {
+                actions.just_pressed.insert(action);
+            }
+            if just_released {
+                actions.just_released.insert(action);
+            }
+        }
+    }
+
+    // A tap works like the pointer button
+    let touch = touches
+        .iter()
+        .chain(touches.iter_just_released())
+        .next()
+        .map(|touch| touch.position());
+    if touches.any_just_pressed() {
+        actions.just_pressed.insert(InputAction::Place);
+    }
+    if touches.iter().next().is_some() {
+        actions.pressed.insert(InputAction::Place);
+    }
+    if touches.any_just_released() {
+        actions.just_released.insert(InputAction::Place);
+    }
+    let cursor = windows
+        .get_single()
+        .ok()
+        .and_then(|window| window.cursor_position());
+    actions.pointer_is_touch = touch.is_some() && cursor.is_none();
+    actions.pointer = cursor.or(touch);
+
+    let axis = |actions: &ActionState, negative, positive| {
+        actions.pressed(positive) as i32 as f32 - actions.pressed(negative) as i32 as f32
+    };
+    actions.pan = Vec2::new(
+        axis(&actions, InputAction::PanLeft, InputAction::PanRight),
+        axis(&actions, InputAction::PanDown, InputAction::PanUp),
+    );
+    actions.zoom_hold = axis(&actions, InputAction::ZoomOut, InputAction::ZoomIn);
+
+    // The left stick pans, the triggers zoom
+    for gamepad in gamepads.iter() {
+        let stick = gamepad.left_stick();
+        if stick.length() > GAMEPAD_DEADZONE {
+            actions.pan += stick;
+        }
+        let trigger = |button| gamepad.get(button).unwrap_or(0.0);
+        let zoom = trigger(GamepadButton::RightTrigger2) - trigger(GamepadButton::LeftTrigger2);
+        if zoom.abs() > GAMEPAD_DEADZONE {
+            actions.zoom_hold += zoom;
+        }
+    }
+    actions.pan = actions.pan.clamp(Vec2::NEG_ONE, Vec2::ONE);
+    actions.zoom_hold = actions.zoom_hold.clamp(-1.0, 1.0);
+    actions.zoom_steps = wheel.read().map(|event| event.y).sum();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn non_keyboard(bindings: Vec<Binding>) -> Vec<Binding> {
+        bindings
+            .into_iter()
+            .filter(|binding| !binding.is_keyboard())
+            .collect()
+    }
+
+    #[test]
+    fn rebind_key_replaces_keyboard_bindings_only() {
+        let mut bindings = Bindings::default();
+        bindings.rebind_key(InputAction::Undo, KeyCode::KeyU);
+
+        let undo = bindings.get(InputAction::Undo);
+        assert_eq!(undo[0], Binding::Key(KeyCode::KeyU));
+        assert_eq!(
+            undo[1..],
+            non_keyboard(Bindings::default().get(InputAction::Undo))
+        );
+        assert_eq!(
+            bindings.get(InputAction::Cancel),
+            Bindings::default().get(InputAction::Cancel)
+        );
+    }
+
+    #[test]
+    fn rebind_key_of_missing_action_starts_from_its_defaults() {
+        let mut bindings = Bindings(BTreeMap::new());
+        bindings.rebind_key(InputAction::Place, KeyCode::KeyP);
+
+        let place = bindings.get(InputAction::Place);
+        assert_eq!(place[0], Binding::Key(KeyCode::KeyP));
+        assert_eq!(
+            place[1..],
+            non_keyboard(Bindings::default().get(InputAction::Place))
+        );
+    }
+}
@@ -2,19 +2,43 @@ use bevy::prelude::*;
 
 mod buildings;
 mod camera;
+mod clan;
+mod combat;
 mod components;
 mod constants;
+mod cursor;
+mod feedback;
 mod game;
+mod heroes;
+mod input;
+mod pathfinding;
+mod save;
+mod settings;
+mod spells;
+mod traps;
 mod ui;
+mod units;
 
 pub mod prelude {
     pub use crate::buildings::*;
     pub use crate::camera::*;
+    pub use crate::clan::*;
+    pub use crate::combat::*;
     pub use crate::components::*;
     pub use crate::constants::*;
+    pub use crate::cursor::*;
+    pub use crate::feedback::*;
     pub use crate::game::*;
+    pub use crate::heroes::*;
+    pub use crate::input::*;
+    pub use crate::pathfinding::*;
+    pub use crate::save::*;
+    pub use crate::settings::*;
+    pub use crate::spells::*;
+    pub use crate::traps::*;
     pub use crate::ui::*;
-    pub use crate::GameState;
+    pub use crate::units::*;
+    pub use crate::{GameState, InGame};
 }
 
 // Game states
@@ -24,5 +48,23 @@ pub enum GameState {
     MainMenu,
     Playing,
     LevelEditor,
+    Attack,
+    /// The village holds off a raid, see [`start_raid`](prelude::start_raid)
+    Defense,
     Paused,
 }
+
+/// Any state with a village loaded, session entities are scoped to it
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct InGame;
+
+impl ComputedStates for InGame {
+    type SourceStates = GameState;
+
+    fn compute(state: GameState) -> Option<Self> {
+        match state {
+            GameState::MainMenu => None,
+            _ => Some(InGame),
+        }
+    }
+}
@@ -1,4 +1,6 @@
-use bevy::prelude::*;
+use bevy::{
+    input::InputSystem, prelude::*, render::view::VisibilitySystems, transform::TransformSystem,
+};
 use coclike::prelude::*;
 
 fn main() {
@@ -6,24 +8,76 @@ fn main() {
     App::new()
         .add_plugins(DefaultPlugins)
         .init_state::<GameState>()
+        .add_computed_state::<InGame>()
+        .enable_state_scoped_entities::<InGame>()
+        .init_resource::<ViewMode>()
+        .init_resource::<SessionStart>()
+        .init_resource::<UiFocus>()
+        .init_resource::<Rebinding>()
+        .init_resource::<ActionState>()
+        .init_resource::<Theme>()
         .add_event::<ButtonInteractionEvent<MenuButton>>()
         .add_event::<ButtonInteractionEvent<EditorButton>>()
-        .add_systems(Startup, setup_camera)
+        .add_event::<ButtonInteractionEvent<HudButton>>()
+        .add_event::<ButtonInteractionEvent<AttackButton>>()
+        .add_event::<ButtonInteractionEvent<InfoButton>>()
+        .add_event::<ButtonInteractionEvent<PauseButton>>()
+        .add_event::<ButtonInteractionEvent<SettingsButton>>()
+        .add_event::<BuildingDestroyed>()
+        .add_event::<WorldClick>()
+        .add_systems(Startup, (load_settings, setup_camera))
+        .add_systems(PreUpdate, update_action_state.after(InputSystem))
+        .add_systems(
+            PostUpdate,
+            project_view
+                .after(TransformSystem::TransformPropagate)
+                .before(VisibilitySystems::CheckVisibility),
+        )
         .add_systems(OnEnter(GameState::MainMenu), setup_menu)
-        .add_systems(OnExit(GameState::MainMenu), cleanup_menu)
-        .add_systems(OnEnter(GameState::LevelEditor), setup_editor)
-        .add_systems(OnExit(GameState::LevelEditor), cleanup_editor)
-        .add_systems(
-            OnTransition {
-                exited: GameState::MainMenu,
-                entered: GameState::Playing,
-            },
+        .add_systems(OnExit(GameState::MainMenu), (cleanup_menu, close_settings))
+        .add_systems(
+            OnEnter(GameState::LevelEditor),
+            setup_editor.run_if(not(resuming)),
+        )
+        .add_systems(
+            OnExit(GameState::LevelEditor),
+            cleanup_editor.run_if(not(pausing)),
+        )
+        .add_systems(
+            OnExit(GameState::Playing),
+            clear_selection.run_if(not(pausing)),
+        )
+        .add_systems(
+            OnEnter(GameState::Attack),
+            (setup_attack_ui, dismiss_hero_guards).run_if(not(resuming)),
+        )
+        .add_systems(
+            OnExit(GameState::Attack),
+            (cleanup_attack_ui, end_battle).run_if(not(pausing)),
+        )
+        .add_systems(
+            OnEnter(GameState::Defense),
+            (setup_defense_ui, start_raid).run_if(not(resuming)),
+        )
+        .add_systems(
+            OnExit(GameState::Defense),
+            (cleanup_defense_ui, end_battle).run_if(not(pausing)),
+        )
+        .add_systems(OnEnter(GameState::Paused), setup_pause_menu)
+        .add_systems(
+            OnExit(GameState::Paused),
+            (cleanup_pause_menu, close_settings),
+        )
+        // Session entities are scoped to InGame, a new session starts clean
+        .add_systems(
+            OnEnter(InGame),
             (
                 setup_game,
                 setup_grid,
                 spawn_initial_buildings,
-                setup_hud,
-                setup_lower_ui,
+                (setup_hud, setup_lower_ui).run_if(in_village),
+                setup_minimap,
+                setup_info_panel,
                 setup_debug_overlay,
             )
                 .chain(),
@@ -33,23 +87,140 @@ fn main() {
             (
                 handle_button_interactions::<MenuButton>,
                 handle_button_interactions::<EditorButton>,
+                handle_button_interactions::<HudButton>,
+                handle_button_interactions::<AttackButton>,
+                handle_button_interactions::<InfoButton>,
+                handle_button_interactions::<PauseButton>,
                 menu_button_handler,
+                (
+                    handle_button_interactions::<SettingsButton>,
+                    capture_rebinding,
+                    settings_button_handler,
+                    apply_setting_sliders,
+                    update_settings_menu.run_if(any_with_component::<SettingsMenuMarker>),
+                    (apply_settings, save_settings).run_if(resource_changed::<Settings>),
+                )
+                    .chain(),
+                // A key being bound doesn't also navigate or pause
+                (
+                    navigate_focus,
+                    toggle_pause,
+                    pause_button_handler.run_if(in_state(GameState::Paused)),
+                )
+                    .before(capture_rebinding),
+                (
+                    apply_theme,
+                    update_disabled_buttons,
+                    update_toggles,
+                    drag_sliders,
+                    update_fills,
+                    scroll_lists,
+                    scroll_to_focus,
+                    show_tooltips,
+                )
+                    .chain()
+                    .before(apply_setting_sliders)
+                    .after(navigate_focus),
+                (
+                    update_trap_visibility,
+                    // Re-routing reads the segments of the walls that fell
+                    recompute_wall_segments.after(reroute_units),
+                    show_lower_hud.run_if(state_changed::<GameState>),
+                    (load_building_atlas, apply_building_sprites).chain(),
+                    regenerate_heroes,
+                    return_heroes,
+                ),
                 (
                     collect_resources,
                     synchronize_buildings_with_map,
-                    camera_movement,
-                    camera_zoom,
                     update_resource_display,
+                    update_brew_progress,
                     update_debug_overlay,
                     toggle_debug_overlay,
+                    hud_button_handler,
+                    brew_spells,
+                    receive_donations,
+                    autosave,
+                    (
+                        select_building,
+                        info_button_handler,
+                        update_info_panel,
+                        draw_selection,
+                    )
+                        .chain(),
                 )
                     .run_if(in_state(GameState::Playing)),
+                (
+                    apply_spell_zones,
+                    tick_spell_effects,
+                    tick_hero_abilities,
+                    release_clan_troops.run_if(in_state(GameState::Defense)),
+                    update_unit_targets,
+                    move_units,
+                    update_defenders,
+                    acquire_defense_targets,
+                    fire_defenses,
+                    fire_beams,
+                    move_projectiles,
+                    trigger_traps,
+                    sync_hero_health,
+                    despawn_dead_units,
+                    destroy_buildings,
+                    reroute_units,
+                    (
+                        track_damage,
+                        update_health_bars,
+                        flash_hits,
+                        float_damage_numbers,
+                        spawn_rubble,
+                    )
+                        .chain(),
+                )
+                    .chain()
+                    .run_if(
+                        in_state(GameState::Playing)
+                            .or(in_state(GameState::Attack))
+                            .or(in_state(GameState::Defense)),
+                    ),
+                (
+                    toggle_view_mode,
+                    toggle_grid,
+                    apply_view_mode,
+                    camera_movement,
+                    camera_touch,
+                    camera_zoom,
+                    camera_pan_keys,
+                    move_grid_cursor,
+                    ease_camera,
+                    clamp_camera,
+                    minimap_click,
+                    update_minimap,
+                    update_minimap_viewport,
+                    draw_grid_cursor,
+                )
+                    .chain()
+                    .run_if(
+                        in_state(GameState::Playing)
+                            .or(in_state(GameState::Attack))
+                            .or(in_state(GameState::Defense))
+                            .or(in_state(GameState::LevelEditor)),
+                    ),
+                (attack_button_handler, deploy_attack_unit).run_if(in_state(GameState::Attack)),
+                (
+                    post_hero_guards,
+                    recall_hero_guards,
+                    update_defense_ui,
+                    end_raid,
+                )
+                    .run_if(in_state(GameState::Defense)),
+                draw_defense_ranges
+                    .run_if(in_state(GameState::Playing).or(in_state(GameState::LevelEditor))),
                 (
                     synchronize_buildings_with_map,
                     editor_button_handler,
+                    editor_actions,
                     place_editor_building,
-                    camera_movement,
-                    camera_zoom,
+                    update_coverage_map,
                 )
                     .run_if(in_state(GameState::LevelEditor)),
             ),
@@ -0,0 +1,203 @@
+//! Grid pathfinding for ground units
+use crate::prelude::*;
+use bevy::prelude::*;
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+const NEIGHBOURS: [(i32, i32); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+/// A* from `start` to any tile occupied by `goal`.
+///
+/// Empty tiles cost 1, occupied tiles cost 1 plus what `extra_cost`
+/// returns for their entity and block the way when it returns `None`.
+/// See [`passage_cost`] for the costs units use. The returned path doesn't
+/// include `start`.
+pub fn find_path(
+    map: &TileMap,
+    start: UVec2,
+    goal: Entity,
+    extra_cost: impl Fn(Entity) -> Option<u32>,
+) -> Option<Vec<UVec2>> {
+    let start_idx = map.get_tile_idx(start.x as usize, start.y as usize)?;
+
+    // Distance to the box around the goal tiles, never more than the
+    // distance to the nearest goal tile
+    let (min, max) = (0..map.tiles.len())
+        .filter(|idx| map.tiles[*idx] == Some(goal))
+        .map(|idx| IVec2::new((idx % map.width) as i32, (idx / map.width) as i32))
+        .fold((IVec2::MAX, IVec2::MIN), |(min, max), tile| {
+            (min.min(tile), max.max(tile))
+        });
+    if min.x > max.x {
+        return None;
+    }
+    let heuristic = |idx: usize| {
+        let tile = IVec2::new((idx % map.width) as i32, (idx / map.width) as i32);
+        let delta = (min - tile).max(tile - max).max(IVec2::ZERO);
+        (delta.x + delta.y) as u32
+    };
+
+    let mut costs = vec![u32::MAX; map.tiles.len()];
+    let mut came_from = vec![usize::MAX; map.tiles.len()];
+    let mut open = BinaryHeap::new();
+    costs[start_idx] = 0;
+    open.push(Reverse((heuristic(start_idx), start_idx)));
+
+    while let Some(Reverse((estimate, idx))) = open.pop() {
+        // Left behind when a cheaper way to the tile was found
+        if estimate > costs[idx] + heuristic(idx) {
+            continue;
+        }
+        if map.tiles[idx] == Some(goal) {
+            let mut path = Vec::new();
+            let mut current = idx;
+            while current != start_idx {
+                path.push(UVec2::new(
+                    (current % map.width) as u32,
+                    (current / map.width) as u32,
+                ));
+                current = came_from[current];
+            }
+            path.reverse();
+            return Some(path);
+        }
+
+        let (x, y) = ((idx % map.width) as i32, (idx / map.width) as i32);
+        for (dx, dy) in NEIGHBOURS {
+            let (nx, ny) = (x + dx, y + dy);
+            if nx < 0 || ny < 0 {
+                continue;
+            }
+            let Some(next) = map.get_tile_idx(nx as usize, ny as usize) else {
+                continue;
+            };
+
+            let step = match map.tiles[next] {
+                None => 1,
+                Some(entity) if entity == goal => 1,
+                Some(entity) => match extra_cost(entity) {
+                    Some(extra) => 1 + extra,
+                    None => continue,
+                },
+            };
+            let cost = costs[idx] + step;
+            if cost < costs[next] {
+                costs[next] = cost;
+                came_from[next] = idx;
+                open.push(Reverse((cost + heuristic(next), next)));
+            }
+        }
+    }
+
+    None
+}
+
+/// Extra cost for a ground unit to cross a tile holding `building_type`.
+///
+/// Walls cost [`WALL_PATH_COST`] so units walk around them when they can
+/// and break through when they can't. Traps are hidden, so units walk
+/// over them like empty ground. Every other building blocks the way.
+pub fn passage_cost(building_type: &BuildingType) -> Option<u32> {
+    match building_type {
+        BuildingType::Wall => Some(WALL_PATH_COST),
+        BuildingType::Trap(_) => Some(0),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::utils::HashMap;
+
+    /// A corridor along the middle row, the rows above and below are a
+    /// building that blocks the way
+    fn corridor(width: usize) -> (TileMap, HashMap<Entity, BuildingType>) {
+        let blocker = Entity::from_raw(100);
+        let mut map = TileMap::new(width, 3);
+        map.place(0, 0, blocker, (width, 1));
+        map.place(0, 2, blocker, (width, 1));
+        (
+            map,
+            HashMap::from([(blocker, BuildingType::Storage(ResourceType::Gold))]),
+        )
+    }
+
+    /// The way units see buildings, see [`passage_cost`]
+    fn route(
+        map: &TileMap,
+        buildings: &HashMap<Entity, BuildingType>,
+        goal: Entity,
+    ) -> Option<Vec<UVec2>> {
+        find_path(map, UVec2::new(0, 1), goal, |entity| {
+            buildings.get(&entity).and_then(passage_cost)
+        })
+    }
+
+    #[test]
+    fn barbarian_walks_over_bomb() {
+        let (mut map, mut buildings) = corridor(5);
+        let (bomb, goal) = (Entity::from_raw(1), Entity::from_raw(2));
+        map.place(2, 1, bomb, (1, 1));
+        map.place(4, 1, goal, (1, 1));
+        buildings.insert(bomb, BuildingType::Trap(TrapKind::Bomb));
+        buildings.insert(goal, BuildingType::TownHall);
+
+        let expected = (1..5).map(|x| UVec2::new(x, 1)).collect::<Vec<_>>();
+        assert_eq!(route(&map, &buildings, goal), Some(expected));
+    }
+
+    #[test]
+    fn breaks_through_a_wall_it_cant_go_around() {
+        let (mut map, mut buildings) = corridor(5);
+        let (wall, goal) = (Entity::from_raw(1), Entity::from_raw(2));
+        map.place(2, 1, wall, (1, 1));
+        map.place(4, 1, goal, (1, 1));
+        buildings.insert(wall, BuildingType::Wall);
+        buildings.insert(goal, BuildingType::TownHall);
+
+        let path = route(&map, &buildings, goal);
+        assert!(path.is_some_and(|path| path.contains(&UVec2::new(2, 1))));
+    }
+
+    #[test]
+    fn walks_around_a_wall_when_it_can() {
+        let mut map = TileMap::new(5, 3);
+        let (wall, goal) = (Entity::from_raw(1), Entity::from_raw(2));
+        map.place(2, 1, wall, (1, 1));
+        map.place(4, 1, goal, (1, 1));
+        let buildings = HashMap::from([(wall, BuildingType::Wall)]);
+
+        let path = route(&map, &buildings, goal).unwrap();
+        assert!(!path.contains(&UVec2::new(2, 1)));
+        assert_eq!(path.last(), Some(&UVec2::new(4, 1)));
+    }
+
+    #[test]
+    fn stops_at_the_first_tile_of_a_large_goal() {
+        let mut map = TileMap::new(8, 3);
+        let goal = Entity::from_raw(1);
+        map.place(4, 0, goal, (3, 3));
+
+        let path = route(&map, &HashMap::new(), goal).unwrap();
+        assert_eq!(path.len(), 4);
+        assert_eq!(path.last(), Some(&UVec2::new(4, 1)));
+    }
+
+    #[test]
+    fn no_path_past_blocking_buildings() {
+        let (mut map, mut buildings) = corridor(5);
+        let (storage, goal) = (Entity::from_raw(1), Entity::from_raw(2));
+        map.place(2, 1, storage, (1, 1));
+        map.place(4, 1, goal, (1, 1));
+        buildings.insert(storage, BuildingType::Storage(ResourceType::Elixir));
+        buildings.insert(goal, BuildingType::TownHall);
+
+        assert_eq!(route(&map, &buildings, goal), None);
+    }
+
+    #[test]
+    fn no_path_to_a_goal_off_the_map() {
+        let map = TileMap::new(5, 3);
+        assert_eq!(route(&map, &HashMap::new(), Entity::from_raw(1)), None);
+    }
+}
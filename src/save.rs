@@ -0,0 +1,162 @@
+//! Village layout and resources written to disk between sessions
+use crate::prelude::*;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{fs, io, path::Path};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SavedBuilding {
+    pub building_type: BuildingType,
+    pub level: u32,
+    pub x: usize,
+    pub y: usize,
+    /// Produced by a collector and not collected yet
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stored: Option<f32>,
+    /// Whether a trap is armed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub armed: Option<bool>,
+}
+
+impl SavedBuilding {
+    /// Puts the saved state back on a building spawned from this save
+    pub fn restore(&self, entity: &mut EntityCommands) {
+        if let Some(stored) = self.stored {
+            entity
+                .entry::<ResourceCollector>()
+                .and_modify(move |mut collector| collector.stored = stored);
+        }
+        if let BuildingType::HeroAltar(_) = self.building_type {
+            let level = self.level;
+            entity.entry::<HeroAltar>().and_modify(move |mut altar| {
+                altar.level = level;
+                altar.health = altar.max_health();
+            });
+        }
+        if let Some(armed) = self.armed {
+            entity
+                .entry::<Trap>()
+                .and_modify(move |mut trap| trap.armed = armed);
+        }
+    }
+}
+
+/// The village layout, building levels, collector contents, trap states and
+/// resources.
+///
+/// Clan castle troops, brewed spells and the brewing queue aren't saved, a
+/// continued village starts without them. Heroes start fully healed.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct SaveGame {
+    pub resources: Vec<(ResourceType, f64)>,
+    pub buildings: Vec<SavedBuilding>,
+}
+
+impl SaveGame {
+    pub fn capture<'a>(
+        resources: &PlayerResources,
+        buildings: impl Iterator<Item = SavedBuilding> + 'a,
+    ) -> Self {
+        SaveGame {
+            resources: resources
+                .resources
+                .iter()
+                .map(|(resource_type, amount)| (*resource_type, *amount))
+                .collect(),
+            buildings: buildings.collect(),
+        }
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let content = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(io::Error::other)?;
+        fs::write(path, content)
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> io::Result<Self> {
+        let content = fs::read_to_string(path)?;
+        ron::from_str(&content).map_err(io::Error::other)
+    }
+}
+
+pub type SaveTarget<'a> = (
+    &'a BuildingType,
+    Option<&'a Building>,
+    &'a GridPosition,
+    Option<&'a HeroAltar>,
+    Option<&'a ResourceCollector>,
+    Option<&'a Trap>,
+);
+pub type SaveFilter = Or<(With<Building>, With<Trap>)>;
+
+/// Writes the village to [`SAVE_PATH`]
+pub fn save_village(
+    resources: &PlayerResources,
+    buildings: &Query<SaveTarget, SaveFilter>,
+) -> io::Result<usize> {
+    let save = SaveGame::capture(
+        resources,
+        buildings.iter().map(
+            |(building_type, building, position, altar, collector, trap)| SavedBuilding {
+                building_type: *building_type,
+                // Hero upgrades live in the altar, traps don't level up
+                level: altar
+                    .map(|altar| altar.level)
+                    .or(building.map(|building| building.level))
+                    .unwrap_or(1),
+                x: position.x,
+                y: position.y,
+                stored: collector.map(|collector| collector.stored),
+                armed: trap.map(|trap| trap.armed),
+            },
+        ),
+    );
+    save.write(SAVE_PATH)?;
+    Ok(save.buildings.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn saved(building_type: BuildingType, x: usize) -> SavedBuilding {
+        SavedBuilding {
+            building_type,
+            level: 2,
+            x,
+            y: 7,
+            stored: None,
+            armed: None,
+        }
+    }
+
+    #[test]
+    fn save_game_round_trips_through_ron() {
+        let save = SaveGame {
+            resources: vec![(ResourceType::Gold, 1234.5), (ResourceType::Elixir, 10.0)],
+            buildings: vec![
+                saved(BuildingType::TownHall, 1),
+                SavedBuilding {
+                    stored: Some(42.0),
+                    ..saved(BuildingType::Collector(ResourceType::Elixir), 5)
+                },
+                SavedBuilding {
+                    armed: Some(false),
+                    ..saved(BuildingType::Trap(TrapKind::SpringTrap), 9)
+                },
+                saved(BuildingType::HeroAltar(HeroType::Queen), 12),
+            ],
+        };
+
+        let content = ron::ser::to_string_pretty(&save, ron::ser::PrettyConfig::default()).unwrap();
+        assert_eq!(ron::from_str::<SaveGame>(&content).unwrap(), save);
+    }
+
+    #[test]
+    fn older_saves_read_without_collector_and_trap_state() {
+        let content = "(resources: [], buildings: [(building_type: Wall, level: 1, x: 3, y: 4)])";
+        let save: SaveGame = ron::from_str(content).unwrap();
+        assert_eq!(save.buildings[0].stored, None);
+        assert_eq!(save.buildings[0].armed, None);
+    }
+}
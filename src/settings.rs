@@ -0,0 +1,334 @@
+//! Player preferences, kept in [`SETTINGS_PATH`] and applied as they change
+use crate::prelude::*;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{fs, io, path::Path};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingKind {
+    PanSpeed,
+    ZoomSpeed,
+    UiScale,
+    AutosaveInterval,
+    ShowGrid,
+    DebugOverlay,
+}
+
+impl SettingKind {
+    pub const STEPPED: [SettingKind; 4] = [
+        SettingKind::PanSpeed,
+        SettingKind::ZoomSpeed,
+        SettingKind::UiScale,
+        SettingKind::AutosaveInterval,
+    ];
+    pub const TOGGLES: [SettingKind; 2] = [SettingKind::ShowGrid, SettingKind::DebugOverlay];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SettingKind::PanSpeed => "Pan Speed",
+            SettingKind::ZoomSpeed => "Zoom Speed",
+            SettingKind::UiScale => "UI Scale",
+            SettingKind::AutosaveInterval => "Autosave (s)",
+            SettingKind::ShowGrid => "Show Grid",
+            SettingKind::DebugOverlay => "Debug Overlay",
+        }
+    }
+}
+
+/// Missing fields in the file fall back to their defaults
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    /// Key and edge panning, in screen pixels per second
+    pub pan_speed: f32,
+    pub zoom_speed: f32,
+    pub ui_scale: f32,
+    pub show_grid: bool,
+    /// Whether the debug overlay starts visible
+    pub debug_overlay: bool,
+    /// Seconds between autosaves of the village, zero turns them off
+    pub autosave_interval: f32,
+    pub bindings: Bindings,
+    /// Keys of settings files from before [`Bindings`], moved over on read
+    #[serde(skip_serializing, deserialize_with = "read_legacy_keybindings")]
+    keybindings: Option<LegacyKeybindings>,
+}
+
+/// The four rebindable keys settings files used to have, written without
+/// the `Some` an `Option` would need
+fn read_legacy_keybindings<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<LegacyKeybindings>, D::Error> {
+    LegacyKeybindings::deserialize(deserializer).map(Some)
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+struct LegacyKeybindings {
+    toggle_debug: KeyCode,
+    toggle_grid: KeyCode,
+    toggle_view: KeyCode,
+    pause: KeyCode,
+}
+
+impl Default for LegacyKeybindings {
+    fn default() -> Self {
+        LegacyKeybindings {
+            toggle_debug: KeyCode::F3,
+            toggle_grid: KeyCode::KeyG,
+            toggle_view: KeyCode::F2,
+            pause: KeyCode::Escape,
+        }
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        let camera = GameCamera::default();
+        Settings {
+            pan_speed: camera.pan_speed,
+            zoom_speed: camera.zoom_speed,
+            ui_scale: 1.0,
+            show_grid: true,
+            debug_overlay: false,
+            autosave_interval: 120.0,
+            bindings: Bindings::default(),
+            keybindings: None,
+        }
+    }
+}
+
+impl Settings {
+    /// Limits and step of a stepped setting
+    pub fn range(kind: SettingKind) -> (f32, f32, f32) {
+        match kind {
+            SettingKind::PanSpeed => (100.0, 2000.0, 100.0),
+            SettingKind::ZoomSpeed => (0.25, 4.0, 0.25),
+            SettingKind::UiScale => (0.5, 2.0, 0.1),
+            SettingKind::AutosaveInterval => (0.0, 600.0, 30.0),
+            SettingKind::ShowGrid | SettingKind::DebugOverlay => (0.0, 1.0, 1.0),
+        }
+    }
+
+    pub fn get(&self, kind: SettingKind) -> f32 {
+        match kind {
+            SettingKind::PanSpeed => self.pan_speed,
+            SettingKind::ZoomSpeed => self.zoom_speed,
+            SettingKind::UiScale => self.ui_scale,
+            SettingKind::AutosaveInterval => self.autosave_interval,
+            SettingKind::ShowGrid => self.show_grid as i32 as f32,
+            SettingKind::DebugOverlay => self.debug_overlay as i32 as f32,
+        }
+    }
+
+    /// Sets a stepped setting, kept within its limits
+    pub fn set(&mut self, kind: SettingKind, value: f32) {
+        let (min, max, _) = Settings::range(kind);
+        let value = value.clamp(min, max);
+        match kind {
+            SettingKind::PanSpeed => self.pan_speed = value,
+            SettingKind::ZoomSpeed => self.zoom_speed = value,
+            SettingKind::UiScale => self.ui_scale = value,
+            SettingKind::AutosaveInterval => self.autosave_interval = value,
+            SettingKind::ShowGrid => self.show_grid = value > 0.5,
+            SettingKind::DebugOverlay => self.debug_overlay = value > 0.5,
+        }
+    }
+
+    /// Moves a stepped setting one step up or down, within its limits
+    pub fn step(&mut self, kind: SettingKind, up: bool) {
+        match kind {
+            SettingKind::ShowGrid | SettingKind::DebugOverlay => self.toggle(kind),
+            _ => {
+                let (_, _, step) = Settings::range(kind);
+                let sign = if up { 1.0 } else { -1.0 };
+                self.set(kind, self.get(kind) + step * sign);
+            }
+        }
+    }
+
+    pub fn toggle(&mut self, kind: SettingKind) {
+        match kind {
+            SettingKind::ShowGrid => self.show_grid = !self.show_grid,
+            SettingKind::DebugOverlay => self.debug_overlay = !self.debug_overlay,
+            _ => (),
+        }
+    }
+
+    pub fn display(&self, kind: SettingKind) -> String {
+        let on_off = |value: bool| if value { "On" } else { "Off" }.to_string();
+        match kind {
+            SettingKind::PanSpeed => format!("{:.0}", self.pan_speed),
+            SettingKind::ZoomSpeed => format!("{:.2}", self.zoom_speed),
+            SettingKind::UiScale => format!("{:.1}", self.ui_scale),
+            SettingKind::AutosaveInterval if self.autosave_interval <= 0.0 => "Off".into(),
+            SettingKind::AutosaveInterval => format!("{:.0}", self.autosave_interval),
+            SettingKind::ShowGrid => on_off(self.show_grid),
+            SettingKind::DebugOverlay => on_off(self.debug_overlay),
+        }
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let content = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(io::Error::other)?;
+        fs::write(path, content)
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> io::Result<Self> {
+        let content = fs::read_to_string(path)?;
+        let mut settings: Settings = ron::from_str(&content).map_err(io::Error::other)?;
+        settings.migrate_keybindings();
+        Ok(settings)
+    }
+
+    /// Carries the keys of an older file over to the actions they drove
+    fn migrate_keybindings(&mut self) {
+        let Some(legacy) = self.keybindings.take() else {
+            return;
+        };
+        let keys = [
+            (InputAction::ToggleDebug, legacy.toggle_debug),
+            (InputAction::ToggleGrid, legacy.toggle_grid),
+            (InputAction::Rotate, legacy.toggle_view),
+            (InputAction::Pause, legacy.pause),
+        ];
+        for (action, key) in keys {
+            self.bindings.rebind_key(action, key);
+        }
+    }
+}
+
+pub fn load_settings(mut commands: Commands) {
+    let settings = match Settings::read(SETTINGS_PATH) {
+        Ok(settings) => settings,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Settings::default(),
+        Err(err) => {
+            warn!("Couldn't read {SETTINGS_PATH}, using defaults: {err}");
+            Settings::default()
+        }
+    };
+    commands.insert_resource(settings);
+}
+
+/// Writes the settings back whenever they change, not when first loaded
+pub fn save_settings(settings: Res<Settings>) {
+    if settings.is_added() {
+        return;
+    }
+    if let Err(err) = settings.write(SETTINGS_PATH) {
+        error!("Couldn't save {SETTINGS_PATH}: {err}");
+    }
+}
+
+pub fn apply_settings(
+    settings: Res<Settings>,
+    mut ui_scale: ResMut<UiScale>,
+    mut camera_query: Query<&mut GameCamera>,
+    mut grid_query: Query<&mut Visibility, With<GridVisual>>,
+) {
+    ui_scale.0 = settings.ui_scale;
+    for mut camera in camera_query.iter_mut() {
+        camera.pan_speed = settings.pan_speed;
+        camera.zoom_speed = settings.zoom_speed;
+    }
+    for mut visibility in grid_query.iter_mut() {
+        *visibility = if settings.show_grid {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+/// Saves the village every `autosave_interval` seconds of play
+pub fn autosave(
+    time: Res<Time>,
+    mut elapsed: Local<f32>,
+    settings: Res<Settings>,
+    start: Res<SessionStart>,
+    resources: Res<PlayerResources>,
+    buildings: Query<SaveTarget, SaveFilter>,
+) {
+    if settings.autosave_interval <= 0.0 || matches!(*start, SessionStart::Sandbox) {
+        return;
+    }
+    *elapsed += time.delta_secs();
+    if *elapsed < settings.autosave_interval {
+        return;
+    }
+    *elapsed = 0.0;
+
+    match save_village(&resources, &buildings) {
+        Ok(count) => info!("Autosaved {count} buildings"),
+        Err(err) => error!("Autosave to {SAVE_PATH} failed: {err}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn step_stops_at_the_limits() {
+        let mut settings = Settings {
+            zoom_speed: 3.9,
+            pan_speed: 100.0,
+            ..default()
+        };
+        settings.step(SettingKind::ZoomSpeed, true);
+        assert_eq!(settings.zoom_speed, 4.0);
+        settings.step(SettingKind::ZoomSpeed, true);
+        assert_eq!(settings.zoom_speed, 4.0);
+
+        settings.step(SettingKind::PanSpeed, false);
+        assert_eq!(settings.pan_speed, 100.0);
+        settings.step(SettingKind::PanSpeed, true);
+        assert_eq!(settings.pan_speed, 200.0);
+    }
+
+    #[test]
+    fn step_flips_toggles() {
+        let mut settings = Settings::default();
+        let shown = settings.show_grid;
+        settings.step(SettingKind::ShowGrid, false);
+        assert_eq!(settings.show_grid, !shown);
+    }
+
+    #[test]
+    fn set_clamps_to_the_range() {
+        let mut settings = Settings::default();
+        settings.set(SettingKind::PanSpeed, 10_000.0);
+        assert_eq!(settings.pan_speed, 2000.0);
+        settings.set(SettingKind::UiScale, 0.0);
+        assert_eq!(settings.ui_scale, 0.5);
+        settings.set(SettingKind::AutosaveInterval, -30.0);
+        assert_eq!(settings.autosave_interval, 0.0);
+    }
+
+    #[test]
+    fn old_keybindings_carry_over() {
+        let content =
+            "(pan_speed: 700.0, keybindings: (toggle_debug: F5, toggle_grid: KeyH, pause: KeyP))";
+        let mut settings: Settings = ron::from_str(content).unwrap();
+        settings.migrate_keybindings();
+
+        assert_eq!(settings.pan_speed, 700.0);
+        assert_eq!(
+            settings.bindings.get(InputAction::ToggleDebug)[0],
+            Binding::Key(KeyCode::F5)
+        );
+        assert_eq!(
+            settings.bindings.get(InputAction::ToggleGrid)[0],
+            Binding::Key(KeyCode::KeyH)
+        );
+        assert_eq!(
+            settings.bindings.get(InputAction::Rotate)[0],
+            Binding::Key(KeyCode::F2)
+        );
+        assert_eq!(
+            settings.bindings.get(InputAction::Pause)[0],
+            Binding::Key(KeyCode::KeyP)
+        );
+        assert_eq!(settings.keybindings, None);
+    }
+}
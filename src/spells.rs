@@ -0,0 +1,164 @@
+//! Spell brewing, deployment and timed effects
+use crate::prelude::*;
+use bevy::prelude::*;
+
+/// Pays for a spell and queues it in the first factory with room left
+pub fn queue_spell(
+    spell: SpellType,
+    resources: &mut PlayerResources,
+    inventory: &SpellInventory,
+    factories: &mut Query<&mut SpellFactory>,
+) -> bool {
+    let brewed = inventory.count();
+    let Some(mut factory) = factories
+        .iter_mut()
+        .find(|factory| brewed + (factory.queue.len() as u32) < factory.capacity)
+    else {
+        return false;
+    };
+
+    let elixir = resources
+        .resources
+        .entry(ResourceType::Elixir)
+        .or_insert(0.0);
+    if *elixir < spell.cost() {
+        return false;
+    }
+    *elixir -= spell.cost();
+    factory.queue.push(spell);
+    true
+}
+
+pub fn brew_spells(
+    time: Res<Time>,
+    mut inventory: ResMut<SpellInventory>,
+    mut query: Query<&mut SpellFactory>,
+) {
+    for mut factory in query.iter_mut() {
+        let Some(&spell) = factory.queue.first() else {
+            continue;
+        };
+
+        factory.progress += time.delta_secs();
+        if factory.progress >= spell.brew_time() {
+            factory.progress = 0.0;
+            factory.queue.remove(0);
+            *inventory.spells.entry(spell).or_insert(0) += 1;
+            info!("Brewed {spell:?}");
+        }
+    }
+}
+
+pub fn cast_spell(
+    commands: &mut Commands,
+    assets: &SpellAssets,
+    spell: SpellType,
+    position: Vec2,
+) -> Entity {
+    let zone = SpellZone::new(spell);
+    let mut entity = commands.spawn((
+        Transform::from_translation(position.extend(SPELL_Z)).with_scale(Vec3::splat(zone.radius)),
+        zone,
+    ));
+    if let Some(handles) = assets.get(&spell) {
+        entity.insert(handles.to_component());
+    }
+    entity.id()
+}
+
+type SpellTarget<'a> = (
+    Entity,
+    &'a Transform,
+    Option<&'a mut Building>,
+    Has<Defense>,
+);
+type SpellTargetFilter = (Without<Destroyed>, Without<Unit>, Without<SpellZone>);
+type SpellUnitFilter = (With<Unit>, Without<SpellZone>, Without<Defender>);
+
+/// Puts the zone effect on everything inside it, the effect lingers for
+/// [`SPELL_LINGER`] after leaving the zone
+pub fn apply_spell_zones(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut zone_query: Query<(Entity, &Transform, &mut SpellZone)>,
+    unit_query: Query<(Entity, &Transform), SpellUnitFilter>,
+    mut building_query: Query<SpellTarget, SpellTargetFilter>,
+) {
+    for (zone_entity, zone_transform, mut zone) in zone_query.iter_mut() {
+        let origin = zone_transform.translation.truncate();
+        let inside = |transform: &Transform| {
+            origin.distance(transform.translation.truncate()) <= zone.radius
+        };
+
+        match zone.spell {
+            SpellType::Lightning => {
+                for (_, transform, building, _) in building_query.iter_mut() {
+                    if let Some(mut building) = building.filter(|_| inside(transform)) {
+                        building.health -= LIGHTNING_DAMAGE;
+                    }
+                }
+            }
+            SpellType::Heal => {
+                for (entity, _) in unit_query.iter().filter(|(_, t)| inside(t)) {
+                    commands.entity(entity).try_insert(Healing {
+                        per_second: HEAL_PER_SECOND,
+                        remaining: SPELL_LINGER,
+                    });
+                }
+            }
+            SpellType::Rage => {
+                for (entity, _) in unit_query.iter().filter(|(_, t)| inside(t)) {
+                    commands.entity(entity).try_insert(Raged {
+                        damage_multiplier: RAGE_DAMAGE_MULTIPLIER,
+                        speed_multiplier: RAGE_SPEED_MULTIPLIER,
+                        remaining: SPELL_LINGER,
+                    });
+                }
+            }
+            SpellType::Freeze => {
+                for (entity, transform, _, is_defense) in building_query.iter_mut() {
+                    if is_defense && inside(transform) {
+                        commands.entity(entity).try_insert(Frozen {
+                            remaining: SPELL_LINGER,
+                        });
+                    }
+                }
+            }
+        }
+
+        zone.remaining -= time.delta_secs();
+        if zone.remaining <= 0.0 {
+            commands.entity(zone_entity).despawn_recursive();
+        }
+    }
+}
+
+pub fn tick_spell_effects(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut healing_query: Query<(Entity, &mut Healing, &mut Unit)>,
+    mut raged_query: Query<(Entity, &mut Raged)>,
+    mut frozen_query: Query<(Entity, &mut Frozen)>,
+) {
+    let delta = time.delta_secs();
+
+    for (entity, mut healing, mut unit) in healing_query.iter_mut() {
+        unit.health = (unit.health + healing.per_second * delta).min(unit.max_health);
+        healing.remaining -= delta;
+        if healing.remaining <= 0.0 {
+            commands.entity(entity).remove::<Healing>();
+        }
+    }
+    for (entity, mut raged) in raged_query.iter_mut() {
+        raged.remaining -= delta;
+        if raged.remaining <= 0.0 {
+            commands.entity(entity).remove::<Raged>();
+        }
+    }
+    for (entity, mut frozen) in frozen_query.iter_mut() {
+        frozen.remaining -= delta;
+        if frozen.remaining <= 0.0 {
+            commands.entity(entity).remove::<Frozen>();
+        }
+    }
+}
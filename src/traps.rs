@@ -0,0 +1,78 @@
+//! Hidden traps, triggering and re-arming
+use crate::prelude::*;
+use bevy::prelude::*;
+
+pub fn trigger_traps(
+    mut trap_query: Query<(Entity, &Transform, &mut Trap)>,
+    mut unit_query: Query<(Entity, &Transform, &mut Unit), Without<Defender>>,
+) {
+    for (entity, transform, mut trap) in trap_query.iter_mut() {
+        if !trap.armed {
+            continue;
+        }
+        let origin = transform.translation.truncate();
+
+        let Some(triggered_by) = unit_query
+            .iter()
+            .find(|(_, unit_transform, unit)| {
+                unit.health > 0.0
+                    && trap.targeting.allows(unit.layer)
+                    && origin.distance(unit_transform.translation.truncate()) <= trap.trigger_radius
+            })
+            .map(|(unit, _, _)| unit)
+        else {
+            continue;
+        };
+
+        debug!(
+            "Trap {entity:?} {:?} triggered by {triggered_by:?}",
+            trap.kind
+        );
+        trap.armed = false;
+
+        if trap.splash_radius <= 0.0 {
+            if let Ok((_, _, mut unit)) = unit_query.get_mut(triggered_by) {
+                unit.health -= trap.damage;
+            }
+            continue;
+        }
+
+        for (_, unit_transform, mut unit) in unit_query.iter_mut() {
+            let distance = origin.distance(unit_transform.translation.truncate());
+            if distance <= trap.splash_radius && trap.targeting.allows(unit.layer) {
+                unit.health -= trap.damage;
+            }
+        }
+    }
+}
+
+/// Attackers can't see armed traps, the owner always can
+pub fn update_trap_visibility(
+    state: Res<State<GameState>>,
+    mut query: Query<(&Trap, &mut Visibility)>,
+) {
+    let attacking = *state.get() == GameState::Attack;
+    for (trap, mut visibility) in query.iter_mut() {
+        let target = if attacking && trap.armed {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+        visibility.set_if_neq(target);
+    }
+}
+
+/// Re-arms every triggered trap the player can pay for, returns how many
+pub fn rearm_traps(resources: &mut PlayerResources, traps: &mut Query<&mut Trap>) -> usize {
+    let mut rearmed = 0;
+    for mut trap in traps.iter_mut().filter(|trap| !trap.armed) {
+        let gold = resources.resources.entry(ResourceType::Gold).or_insert(0.0);
+        if *gold < trap.rearm_cost {
+            continue;
+        }
+        *gold -= trap.rearm_cost;
+        trap.armed = true;
+        rearmed += 1;
+    }
+    rearmed
+}
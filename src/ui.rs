@@ -1,13 +1,29 @@
+pub mod attack;
 pub mod common;
+pub mod coverage;
 pub mod debug;
+pub mod defense;
 pub mod editor;
 pub mod events;
+pub mod focus;
 pub mod hud;
 pub mod main_menu;
+pub mod minimap;
+pub mod pause;
+pub mod selection;
+pub mod settings_menu;
 
+pub use attack::*;
 pub use common::*;
+pub use coverage::*;
 pub use debug::*;
+pub use defense::*;
 pub use editor::*;
 pub use events::*;
+pub use focus::*;
 pub use hud::*;
 pub use main_menu::*;
+pub use minimap::*;
+pub use pause::*;
+pub use selection::*;
+pub use settings_menu::*;
@@ -0,0 +1,203 @@
+use crate::prelude::*;
+use bevy::prelude::*;
+
+pub fn setup_attack_ui(mut commands: Commands) {
+    commands
+        .spawn((whole_screen(), AttackUIMarker))
+        .with_children(|parent| {
+            // Bottom panel for unit selection
+            parent
+                .spawn(toolbar(Node {
+                    height: Val::Px(80.0),
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(0.0),
+                    left: Val::Px(160.0),
+                    right: Val::Px(0.0),
+                    ..default()
+                }))
+                .with_children(|parent| {
+                    for unit in UnitType::ALL {
+                        spawn_deploy_button(parent, unit.name(), Deployable::Unit(unit));
+                    }
+                    for hero in HeroType::ALL {
+                        let unit = UnitType::Hero(hero);
+                        spawn_deploy_button(parent, unit.name(), Deployable::Unit(unit));
+                    }
+                    spawn_deploy_button(parent, "Clan Castle", Deployable::Reinforcements);
+                    for spell in SpellType::ALL {
+                        spawn_deploy_button(parent, spell.name(), Deployable::Spell(spell));
+                    }
+                });
+
+            parent
+                .spawn((corner_button(0), AttackButton::EndBattle))
+                .with_children(|parent| {
+                    parent.spawn(text("End Battle"));
+                });
+            // Hero abilities stacked above it
+            for (i, hero) in HeroType::ALL.into_iter().enumerate() {
+                parent
+                    .spawn((corner_button(i + 1), AttackButton::HeroAbility(hero)))
+                    .with_children(|parent| {
+                        parent.spawn(text(&format!("{} Ability", hero.name())));
+                    });
+            }
+        });
+
+    commands.insert_resource(AttackState::default());
+}
+
+fn spawn_deploy_button(parent: &mut ChildBuilder, name: &str, deployable: Deployable) {
+    parent
+        .spawn((standard_button(), AttackButton::Deploy(deployable)))
+        .with_children(|parent| {
+            parent.spawn(text(name));
+        });
+}
+
+pub fn cleanup_attack_ui(mut commands: Commands, query: Query<Entity, With<AttackUIMarker>>) {
+    info!(
+        "Doing attack cleanup of: {} items",
+        query.iter().remaining().count()
+    );
+
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Triggered bv [`handle_button_interactions`]
+pub fn attack_button_handler(
+    mut commands: Commands,
+    mut events: EventReader<ButtonInteractionEvent<AttackButton>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut attack_state: ResMut<AttackState>,
+    mut heroes: Query<(Entity, &mut Hero), Without<Defender>>,
+) {
+    for event in events.read() {
+        match event {
+            ButtonInteractionEvent::Pressed(button) => match button {
+                AttackButton::EndBattle => next_state.set(GameState::Playing),
+                AttackButton::Deploy(deployable) => {
+                    attack_state.selected = Some(*deployable);
+                    attack_state.is_selected = true;
+                }
+                AttackButton::HeroAbility(hero) => {
+                    attack_state.is_selected = true;
+                    if !activate_hero_ability(&mut commands, *hero, &mut heroes) {
+                        info!("{hero:?} isn't out or the ability is recharging");
+                    }
+                }
+            },
+            ButtonInteractionEvent::None => {
+                attack_state.is_selected = false;
+            }
+            _ => (),
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn deploy_attack_unit(
+    mut commands: Commands,
+    mut clicks: EventReader<WorldClick>,
+    map_query: Query<&TileMap>,
+    mut attack_state: ResMut<AttackState>,
+    assets: Res<UnitAssets>,
+    spell_assets: Res<SpellAssets>,
+    mut inventory: ResMut<SpellInventory>,
+    mut altars: Query<(Entity, &mut HeroAltar), Without<Destroyed>>,
+    mut castles: Query<&mut ClanCastle, Without<Destroyed>>,
+    trap_query: Query<(), With<Trap>>,
+) {
+    // Drags only move the camera
+    let Some(world_pos) = clicks.read().last().map(|click| click.position) else {
+        return;
+    };
+    if attack_state.is_selected {
+        return;
+    }
+    let Some(deployable) = attack_state.selected else {
+        return;
+    };
+
+    // Spells can land anywhere, on top of buildings too
+    let unit_type = match deployable {
+        Deployable::Unit(unit_type) => Some(unit_type),
+        Deployable::Reinforcements => None,
+        Deployable::Spell(spell) => {
+            if inventory.take(spell) {
+                cast_spell(&mut commands, &spell_assets, spell, world_pos);
+                info!("Cast {spell:?} at {world_pos}");
+            } else {
+                info!("No {spell:?} brewed");
+            }
+            return;
+        }
+    };
+
+    // Units can't be dropped on top of buildings, traps are hidden
+    let Ok(map) = map_query.get_single() else {
+        return;
+    };
+    if world_pos.x < 0.0 || world_pos.y < 0.0 {
+        return;
+    }
+    let (grid_x, grid_y) = (world_pos.x as usize, world_pos.y as usize);
+    let blocked = map
+        .get_entity_at(grid_x, grid_y)
+        .is_some_and(|entity| !trap_query.contains(entity));
+    if map.get_tile_idx(grid_x, grid_y).is_none() || blocked {
+        return;
+    }
+
+    // Clan troops all come out together, the castle is empty afterwards
+    let Some(unit_type) = unit_type else {
+        let troops = take_reinforcements(&mut castles);
+        if troops.is_empty() {
+            info!("No troops in the clan castle");
+            return;
+        }
+        for troop in &troops {
+            deploy_unit(&mut commands, &assets, *troop, 1, world_pos);
+        }
+        info!(
+            "Deployed {} clan troops at ({}, {})",
+            troops.len(),
+            grid_x,
+            grid_y
+        );
+        return;
+    };
+
+    // Each hero joins once per battle, and only when fully rested
+    if let UnitType::Hero(hero_type) = unit_type {
+        if attack_state.deployed_heroes.contains(&hero_type) {
+            info!("{hero_type:?} was already deployed");
+            return;
+        }
+        let Some((altar_entity, mut altar)) = altars
+            .iter_mut()
+            .find(|(_, altar)| altar.hero == hero_type && altar.is_ready())
+        else {
+            info!("{hero_type:?} is not ready");
+            return;
+        };
+        let entity = hero(
+            &mut commands,
+            &assets,
+            hero_type,
+            altar.level,
+            altar.health,
+            world_pos,
+            Some(altar_entity),
+        );
+        altar.deployed = Some(entity);
+        attack_state.deployed_heroes.push(hero_type);
+        info!("Deployed {:?} at ({}, {})", hero_type, grid_x, grid_y);
+        return;
+    }
+
+    deploy_unit(&mut commands, &assets, unit_type, 1, world_pos);
+    info!("Deployed {:?} at ({}, {})", unit_type, grid_x, grid_y);
+}
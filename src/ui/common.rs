@@ -5,8 +5,12 @@ use crate::prelude::*;
 use bevy::prelude::*;
 
 pub mod components;
+pub mod theme;
+pub mod widgets;
 
 pub use self::components::*;
+pub use self::theme::*;
+pub use self::widgets::*;
 
 pub fn whole_screen_center() -> Node {
     Node {
@@ -31,9 +35,10 @@ pub fn title_text(text: &str) -> impl Bundle {
     (
         Text(text.into()),
         TextFont {
-            font_size: 80.0,
+            font_size: TITLE_FONT_SIZE,
             ..default()
         },
+        ThemeText::Title,
     )
 }
 
@@ -41,14 +46,15 @@ pub fn big_text(text: &str) -> impl Bundle {
     (
         Text(text.into()),
         TextFont {
-            font_size: 40.0,
+            font_size: HEADING_FONT_SIZE,
             ..default()
         },
+        ThemeText::Heading,
     )
 }
 
 pub fn text(text: &str) -> impl Bundle {
-    (Text(text.into()),)
+    (Text(text.into()), ThemeText::Body)
 }
 
 pub fn standard_button() -> impl Bundle {
@@ -62,9 +68,64 @@ pub fn standard_button() -> impl Bundle {
             ..default()
         },
         ColorPalette::new_with_bg(GRAY_35, GRAY_25, GRAY_15),
+        ThemeColor::Button,
     )
 }
 
+/// Button sized to its label, for rows of many actions
+pub fn compact_button() -> impl Bundle {
+    (
+        Button,
+        Node {
+            padding: UiRect::all(Val::Px(10.0)),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        ColorPalette::new_with_bg(GRAY_35, GRAY_25, GRAY_15),
+        ThemeColor::Button,
+    )
+}
+
+/// Button pinned to the bottom left corner, `row` buttons up from the bottom.
+/// Row 0 is the way out of the screen
+pub fn corner_button(row: usize) -> impl Bundle {
+    let palette = match row {
+        0 => ColorPalette::new_with_bg(BLUE_6, BLUE_4, BLUE_2),
+        _ => ColorPalette::new_with_bg(BLUE_9, BLUE_7, BLUE_5),
+    };
+    (
+        Button,
+        Node {
+            width: BUTTON_WIDTH,
+            height: BUTTON_HEIGHT,
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(20.0 + 60.0 * row as f32),
+            left: Val::Px(20.0),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        palette,
+    )
+}
+
+/// Panel sized and placed by `node`
+pub fn panel(node: Node) -> impl Bundle {
+    (node, BackgroundColor::default(), ThemeColor::Panel)
+}
+
+/// Panel laying its buttons out in a row, sized and placed by `node`
+pub fn toolbar(node: Node) -> impl Bundle {
+    panel(Node {
+        flex_direction: FlexDirection::Row,
+        justify_content: JustifyContent::SpaceEvenly,
+        align_items: AlignItems::Center,
+        padding: UiRect::all(Val::Px(10.0)),
+        ..node
+    })
+}
+
 pub fn create_panel(width: Val, height: Val) -> impl Bundle {
     (
         Node {
@@ -74,5 +135,6 @@ pub fn create_panel(width: Val, height: Val) -> impl Bundle {
             ..default()
         },
         BackgroundColor(PANEL_BG_COLOR),
+        ThemeColor::Panel,
     )
 }
@@ -34,14 +34,67 @@ impl ColorPalette {
 /// Button types
 #[derive(Component, Debug, Clone, Copy)]
 pub enum MenuButton {
-    Play,
+    Continue,
+    NewGame,
+    ConfirmNewGame,
+    CancelNewGame,
+    /// Editor on an empty map, from the main menu
+    Sandbox,
+    /// Editor on the village, from the HUD
     Editor,
+    Attack,
+    /// Raid on the village, from the HUD
+    Defend,
+    Settings,
     Quit,
 }
 
+#[derive(Component, Debug, Clone, Copy)]
+pub enum HudButton {
+    RearmTraps,
+    BrewSpell(SpellType),
+    UpgradeHero(HeroType),
+    RequestTroops,
+}
+
+#[derive(Component, Debug, Clone, Copy)]
+pub enum AttackButton {
+    Deploy(Deployable),
+    HeroAbility(HeroType),
+    EndBattle,
+}
+
+#[derive(Component, Debug, Clone, Copy)]
+pub enum PauseButton {
+    Resume,
+    Settings,
+    Save,
+    MainMenu,
+    Quit,
+}
+
+#[derive(Component, Debug, Clone, Copy)]
+pub enum SettingsButton {
+    Decrease(SettingKind),
+    Increase(SettingKind),
+    Toggle(SettingKind),
+    Rebind(InputAction),
+    Reset,
+    Close,
+}
+
+#[derive(Component, Debug, Clone, Copy)]
+pub enum InfoButton {
+    Upgrade,
+    Collect,
+    Move,
+    Remove,
+}
+
 #[derive(Component, Debug, Clone, Copy)]
 pub enum EditorButton {
     Building(BuildingType),
+    Coverage,
     Back,
 }
 
@@ -51,17 +104,93 @@ pub enum EditorButton {
 pub struct MainMenuMarker;
 
 #[derive(Component, Debug, Clone, Copy)]
+pub struct ConfirmDialogMarker;
+
+#[derive(Component, Debug, Clone, Copy)]
+pub struct SettingsMenuMarker;
+
+/// Text showing the current value of a setting
+#[derive(Component, Debug, Clone, Copy)]
+pub struct SettingText(pub SettingKind);
+
+/// Slider changing a stepped setting
+#[derive(Component, Debug, Clone, Copy)]
+pub struct SettingSlider(pub SettingKind);
+
+/// Text showing the key bound to an action
+#[derive(Component, Debug, Clone, Copy)]
+pub struct BindingText(pub InputAction);
+
+/// Action waiting for its new key, the next key pressed is bound to it
+#[derive(Resource, Debug, Default)]
+pub struct Rebinding(pub Option<InputAction>);
+
+/// Buttons under the topmost scope, the highest [`GlobalZIndex`] then the
+/// innermost, can be reached with the keyboard or a gamepad
+#[derive(Component, Debug, Clone, Copy)]
+pub struct FocusScope;
+
+#[derive(Resource, Debug, Default)]
+pub struct UiFocus {
+    pub focused: Option<Entity>,
+}
+
+#[derive(Component, Debug, Clone, Copy)]
+#[require(GameWorldMarker)]
 pub struct GameHUDMarker;
 
+#[derive(Component, Debug, Clone, Copy)]
+pub struct PauseMenuMarker;
+
 #[derive(Component, Debug, Clone, Copy)]
 pub struct ResourceDisplayMarker;
 
+/// Village actions, only shown while playing
+#[derive(Component, Debug, Clone, Copy)]
+pub struct LowerHUDMarker;
+
+/// Progress of the spell being brewed
+#[derive(Component, Debug, Clone, Copy)]
+pub struct BrewProgressMarker;
+
 #[derive(Component, Debug, Clone, Copy)]
 pub struct DebugOverlayMarker;
 
 #[derive(Component, Debug, Clone, Copy)]
+#[require(GameWorldMarker)]
+pub struct MinimapMarker;
+
+#[derive(Component, Debug, Clone, Copy)]
+pub struct InfoPanelMarker;
+
+#[derive(Component, Debug, Clone, Copy)]
+pub struct CoverageMarker;
+
+/// Text of the editor button cycling the coverage heatmap
+#[derive(Component, Debug, Clone, Copy)]
+pub struct CoverageLabelMarker;
+
+#[derive(Component, Debug, Clone, Copy)]
+pub struct InfoTextMarker;
+
+#[derive(Component, Debug, Clone, Copy)]
+pub struct MinimapViewportMarker;
+
+#[derive(Component, Debug, Clone, Copy)]
+#[require(GameWorldMarker)]
 pub struct BuildUIMarker;
 
+#[derive(Component, Debug, Clone, Copy)]
+#[require(GameWorldMarker)]
+pub struct AttackUIMarker;
+
+#[derive(Component, Debug, Clone, Copy)]
+#[require(GameWorldMarker)]
+pub struct DefenseUIMarker;
+
+#[derive(Component, Debug, Clone, Copy)]
+pub struct RaidersLeftMarker;
+
 // Resources
 
 #[derive(Resource, Default)]
@@ -0,0 +1,125 @@
+//! Colors and font sizes shared by the widgets, changing the [`Theme`]
+//! restyles everything built with them
+use crate::prelude::*;
+use bevy::prelude::*;
+
+#[derive(Resource, Debug, Clone)]
+pub struct Theme {
+    pub button: ColorPalette,
+    pub disabled: Color,
+    pub panel: Color,
+    /// Dims the screen behind modal dialogs
+    pub overlay: Color,
+    /// Empty part of sliders, toggles and progress bars
+    pub track: Color,
+    pub accent: Color,
+    /// Borders around panels and images
+    pub frame: Color,
+    pub text: Color,
+    pub text_disabled: Color,
+    pub title_size: f32,
+    pub heading_size: f32,
+    pub body_size: f32,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            button: ColorPalette {
+                pressed: GRAY_35,
+                hovered: GRAY_25,
+                none: GRAY_15,
+            },
+            disabled: DISABLED_COLOR,
+            panel: PANEL_BG_COLOR,
+            overlay: OVERLAY_COLOR,
+            track: TRACK_COLOR,
+            accent: ACCENT_COLOR,
+            frame: FRAME_COLOR,
+            text: Color::WHITE,
+            text_disabled: DISABLED_TEXT_COLOR,
+            title_size: TITLE_FONT_SIZE,
+            heading_size: HEADING_FONT_SIZE,
+            body_size: BODY_FONT_SIZE,
+        }
+    }
+}
+
+/// Background taken from the theme
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThemeColor {
+    Button,
+    Panel,
+    Overlay,
+    Track,
+    Accent,
+}
+
+/// Border color taken from the theme
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThemeBorder {
+    Frame,
+    Accent,
+}
+
+/// Font size and color taken from the theme
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThemeText {
+    Title,
+    Heading,
+    Body,
+}
+
+type ThemedNode<'a> = (
+    Ref<'a, ThemeColor>,
+    &'a mut BackgroundColor,
+    Option<&'a mut ColorPalette>,
+    Has<Disabled>,
+);
+type ThemedBorder<'a> = (Ref<'a, ThemeBorder>, &'a mut BorderColor);
+type ThemedText<'a> = (Ref<'a, ThemeText>, &'a mut TextFont, &'a mut TextColor);
+
+/// Styles new widgets, and all of them when the theme changes
+pub fn apply_theme(
+    theme: Res<Theme>,
+    mut nodes: Query<ThemedNode>,
+    mut borders: Query<ThemedBorder>,
+    mut texts: Query<ThemedText>,
+) {
+    for (role, mut background, palette, disabled) in nodes.iter_mut() {
+        if !theme.is_changed() && !role.is_added() {
+            continue;
+        }
+        background.0 = match *role {
+            ThemeColor::Button if disabled => theme.disabled,
+            ThemeColor::Button => theme.button.none,
+            ThemeColor::Panel => theme.panel,
+            ThemeColor::Overlay => theme.overlay,
+            ThemeColor::Track => theme.track,
+            ThemeColor::Accent => theme.accent,
+        };
+        if let (ThemeColor::Button, Some(mut palette)) = (*role, palette) {
+            *palette = theme.button;
+        }
+    }
+    for (role, mut border) in borders.iter_mut() {
+        if !theme.is_changed() && !role.is_added() {
+            continue;
+        }
+        border.0 = match *role {
+            ThemeBorder::Frame => theme.frame,
+            ThemeBorder::Accent => theme.accent,
+        };
+    }
+    for (role, mut font, mut color) in texts.iter_mut() {
+        if !theme.is_changed() && !role.is_added() {
+            continue;
+        }
+        font.font_size = match *role {
+            ThemeText::Title => theme.title_size,
+            ThemeText::Heading => theme.heading_size,
+            ThemeText::Body => theme.body_size,
+        };
+        color.0 = theme.text;
+    }
+}
@@ -0,0 +1,469 @@
+//! Reusable widgets, styled by the [`Theme`]. Each one is a bundle or spawn
+//! function plus the systems keeping it up to date, screens only attach
+//! their own button or marker component
+use crate::prelude::*;
+use bevy::{
+    input::mouse::{MouseScrollUnit, MouseWheel},
+    prelude::*,
+    ui::{FocusPolicy, RelativeCursorPosition},
+};
+
+/// Greys a button out, it sends no [`ButtonInteractionEvent`] and can't be
+/// focused
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Disabled;
+
+/// On/off switch, a button whose knob slides to the right when on. The
+/// screen owning it flips `on` from its button handler
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Toggle {
+    pub on: bool,
+}
+
+#[derive(Component, Debug, Clone, Copy)]
+pub struct ToggleKnob;
+
+/// Value picked by dragging along a track, snapped to `step`
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+#[require(Interaction, RelativeCursorPosition)]
+pub struct Slider {
+    pub value: f32,
+    pub min: f32,
+    pub max: f32,
+    pub step: f32,
+}
+
+impl Slider {
+    pub fn new(value: f32, min: f32, max: f32, step: f32) -> Self {
+        Slider {
+            value,
+            min,
+            max,
+            step,
+        }
+    }
+
+    /// Where the value sits between `min` and `max`, from 0 to 1
+    pub fn fraction(&self) -> f32 {
+        if self.max > self.min {
+            ((self.value - self.min) / (self.max - self.min)).clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+
+    fn value_at(&self, fraction: f32) -> f32 {
+        let value = self.min + fraction.clamp(0.0, 1.0) * (self.max - self.min);
+        let snapped = if self.step > 0.0 {
+            self.min + ((value - self.min) / self.step).round() * self.step
+        } else {
+            value
+        };
+        snapped.clamp(self.min, self.max)
+    }
+}
+
+/// Filled part of a slider or progress bar
+#[derive(Component, Debug, Clone, Copy)]
+pub struct WidgetFill;
+
+/// Completion from 0 to 1
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct ProgressBar(pub f32);
+
+/// Column that scrolls with the mouse wheel, and to keep the focused button
+/// in view
+#[derive(Component, Debug, Clone, Copy)]
+#[require(RelativeCursorPosition)]
+pub struct ScrollList;
+
+/// Text shown next to the cursor while it is over the entity
+#[derive(Component, Debug, Clone)]
+#[require(RelativeCursorPosition)]
+pub struct Tooltip(pub String);
+
+#[derive(Component, Debug, Clone, Copy)]
+pub struct TooltipMarker;
+
+pub fn spawn_toggle(parent: &mut ChildBuilder, on: bool, button: impl Bundle) -> Entity {
+    parent
+        .spawn((
+            Button,
+            Node {
+                width: Val::Px(56.0),
+                height: Val::Px(28.0),
+                padding: UiRect::all(Val::Px(4.0)),
+                ..default()
+            },
+            ColorPalette::new_with_bg(GRAY_35, GRAY_25, GRAY_15),
+            ThemeColor::Button,
+            Toggle { on },
+            button,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Node {
+                    width: Val::Px(20.0),
+                    height: Val::Px(20.0),
+                    ..default()
+                },
+                BackgroundColor::default(),
+                ToggleKnob,
+            ));
+        })
+        .id()
+}
+
+pub fn spawn_slider(parent: &mut ChildBuilder, slider: Slider, marker: impl Bundle) -> Entity {
+    parent
+        .spawn((
+            Node {
+                width: Val::Px(160.0),
+                height: Val::Px(16.0),
+                ..default()
+            },
+            BackgroundColor::default(),
+            ThemeColor::Track,
+            slider,
+            marker,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Node {
+                    width: Val::Percent(slider.fraction() * 100.0),
+                    height: Val::Percent(100.0),
+                    ..default()
+                },
+                BackgroundColor::default(),
+                ThemeColor::Accent,
+                WidgetFill,
+            ));
+        })
+        .id()
+}
+
+pub fn spawn_progress_bar(parent: &mut ChildBuilder, width: Val, marker: impl Bundle) -> Entity {
+    parent
+        .spawn((
+            Node {
+                width,
+                height: Val::Px(12.0),
+                ..default()
+            },
+            BackgroundColor::default(),
+            ThemeColor::Track,
+            ProgressBar(0.0),
+            marker,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Node {
+                    width: Val::Percent(0.0),
+                    height: Val::Percent(100.0),
+                    ..default()
+                },
+                BackgroundColor::default(),
+                ThemeColor::Accent,
+                WidgetFill,
+            ));
+        })
+        .id()
+}
+
+/// Column clipped to `max_height`, the rest is reached by scrolling
+pub fn scroll_list(max_height: Val) -> impl Bundle {
+    (
+        Node {
+            flex_direction: FlexDirection::Column,
+            max_height,
+            overflow: Overflow::scroll_y(),
+            ..default()
+        },
+        ScrollList,
+    )
+}
+
+/// Dims the whole screen and keeps clicks from the UI underneath, out of the
+/// layout of its parent if it has one
+pub fn modal_overlay(z_index: i32) -> impl Bundle {
+    (
+        Node {
+            position_type: PositionType::Absolute,
+            ..whole_screen_center()
+        },
+        BackgroundColor::default(),
+        ThemeColor::Overlay,
+        GlobalZIndex(z_index),
+        FocusPolicy::Block,
+        Interaction::default(),
+        FocusScope,
+    )
+}
+
+/// Dialog over everything else, with a title above its content. The root is
+/// returned, despawning it closes the dialog
+pub fn spawn_modal(
+    commands: &mut Commands,
+    z_index: i32,
+    title: &str,
+    content: impl FnOnce(&mut ChildBuilder),
+) -> Entity {
+    commands
+        .spawn(modal_overlay(z_index))
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    Node {
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(8.0),
+                        padding: UiRect::all(Val::Px(20.0)),
+                        ..default()
+                    },
+                    BackgroundColor::default(),
+                    ThemeColor::Panel,
+                ))
+                .with_children(|parent| {
+                    parent.spawn(big_text(title));
+                    content(parent);
+                });
+        })
+        .id()
+}
+
+/// Greys out buttons when they get disabled, restores them after
+pub fn update_disabled_buttons(
+    theme: Res<Theme>,
+    mut removed: RemovedComponents<Disabled>,
+    added: Query<Entity, Added<Disabled>>,
+    disabled: Query<Entity, With<Disabled>>,
+    mut buttons: Query<(&ColorPalette, &mut BackgroundColor, Has<Disabled>)>,
+    children: Query<&Children>,
+    mut texts: Query<&mut TextColor>,
+) {
+    let mut changed: Vec<Entity> = removed.read().chain(added.iter()).collect();
+    if theme.is_changed() {
+        changed.extend(disabled.iter());
+    }
+    for entity in changed {
+        let Ok((palette, mut background, is_disabled)) = buttons.get_mut(entity) else {
+            continue;
+        };
+        let (color, text_color) = if is_disabled {
+            (theme.disabled, theme.text_disabled)
+        } else {
+            (palette.none, theme.text)
+        };
+        background.0 = color;
+        for child in children.iter_descendants(entity) {
+            if let Ok(mut text) = texts.get_mut(child) {
+                text.0 = text_color;
+            }
+        }
+    }
+}
+
+pub fn update_toggles(
+    theme: Res<Theme>,
+    mut toggles: Query<(Ref<Toggle>, &mut Node, &Children)>,
+    mut knobs: Query<&mut BackgroundColor, With<ToggleKnob>>,
+) {
+    for (toggle, mut node, children) in toggles.iter_mut() {
+        if !toggle.is_changed() && !theme.is_changed() {
+            continue;
+        }
+        node.justify_content = if toggle.on {
+            JustifyContent::FlexEnd
+        } else {
+            JustifyContent::FlexStart
+        };
+        for child in children.iter() {
+            if let Ok(mut knob) = knobs.get_mut(*child) {
+                knob.0 = if toggle.on {
+                    theme.accent
+                } else {
+                    theme.text_disabled
+                };
+            }
+        }
+    }
+}
+
+/// Moves pressed sliders to the pointer
+pub fn drag_sliders(mut sliders: Query<(&Interaction, &RelativeCursorPosition, &mut Slider)>) {
+    for (interaction, cursor, mut slider) in sliders.iter_mut() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let Some(position) = cursor.normalized else {
+            continue;
+        };
+        let value = slider.value_at(position.x);
+        if value != slider.value {
+            slider.value = value;
+        }
+    }
+}
+
+type FillSource<'a> = (Option<&'a Slider>, Option<&'a ProgressBar>, &'a Children);
+type FillChanged = Or<(Changed<Slider>, Changed<ProgressBar>)>;
+
+/// Sizes the fill of sliders and progress bars to their value
+pub fn update_fills(
+    sources: Query<FillSource, FillChanged>,
+    mut fills: Query<&mut Node, With<WidgetFill>>,
+) {
+    for (slider, progress, children) in sources.iter() {
+        let fraction = match (slider, progress) {
+            (Some(slider), _) => slider.fraction(),
+            (None, Some(progress)) => progress.0.clamp(0.0, 1.0),
+            (None, None) => continue,
+        };
+        for child in children.iter() {
+            if let Ok(mut node) = fills.get_mut(*child) {
+                node.width = Val::Percent(fraction * 100.0);
+            }
+        }
+    }
+}
+
+/// Scrolls the list under the cursor with the mouse wheel
+pub fn scroll_lists(
+    mut wheel: EventReader<MouseWheel>,
+    mut lists: Query<(&RelativeCursorPosition, &mut ScrollPosition), With<ScrollList>>,
+) {
+    let scrolled: f32 = wheel
+        .read()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y * SCROLL_LINE_HEIGHT,
+            MouseScrollUnit::Pixel => event.y,
+        })
+        .sum();
+    if scrolled == 0.0 {
+        return;
+    }
+    for (cursor, mut position) in lists.iter_mut() {
+        if cursor.mouse_over() {
+            // Layout clamps the far end to the content
+            position.offset_y = (position.offset_y - scrolled).max(0.0);
+        }
+    }
+}
+
+type ScrollTarget<'a> = (
+    &'a mut ScrollPosition,
+    &'a GlobalTransform,
+    &'a ComputedNode,
+);
+
+/// Scrolls the focused button into view when it is inside a [`ScrollList`]
+pub fn scroll_to_focus(
+    focus: Res<UiFocus>,
+    parents: Query<&Parent>,
+    nodes: Query<(&GlobalTransform, &ComputedNode), Without<ScrollList>>,
+    mut lists: Query<ScrollTarget, With<ScrollList>>,
+) {
+    if !focus.is_changed() {
+        return;
+    }
+    let Some(focused) = focus.focused else {
+        return;
+    };
+    let Some(list) = parents
+        .iter_ancestors(focused)
+        .find(|ancestor| lists.contains(*ancestor))
+    else {
+        return;
+    };
+    let (Ok((transform, node)), Ok((mut position, list_transform, list_node))) =
+        (nodes.get(focused), lists.get_mut(list))
+    else {
+        return;
+    };
+
+    // Both in physical pixels, the scroll offset is in logical ones
+    let top = transform.translation().y - node.size().y / 2.0;
+    let bottom = transform.translation().y + node.size().y / 2.0;
+    let list_top = list_transform.translation().y - list_node.size().y / 2.0;
+    let list_bottom = list_transform.translation().y + list_node.size().y / 2.0;
+    let scale = list_node.inverse_scale_factor();
+    if top < list_top {
+        position.offset_y -= (list_top - top) * scale;
+    } else if bottom > list_bottom {
+        position.offset_y += (bottom - list_bottom) * scale;
+    }
+}
+
+/// One tooltip at a time, following the cursor over the hovered entity
+pub fn show_tooltips(
+    mut commands: Commands,
+    windows: Query<&Window>,
+    ui_scale: Res<UiScale>,
+    sources: Query<(&Tooltip, &RelativeCursorPosition, &InheritedVisibility)>,
+    mut shown: Query<(Entity, &mut Node, &mut Text), With<TooltipMarker>>,
+) {
+    let hovered = sources
+        .iter()
+        .find(|(_, cursor, visibility)| visibility.get() && cursor.mouse_over());
+    let cursor = windows
+        .get_single()
+        .ok()
+        .and_then(|window| window.cursor_position());
+    let (Some((tooltip, ..)), Some(cursor)) = (hovered, cursor) else {
+        for (entity, ..) in shown.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    };
+
+    let position = cursor / ui_scale.0 + TOOLTIP_OFFSET;
+    if let Ok((_, mut node, mut text)) = shown.get_single_mut() {
+        node.left = Val::Px(position.x);
+        node.top = Val::Px(position.y);
+        if text.0 != tooltip.0 {
+            text.0 = tooltip.0.clone();
+        }
+        return;
+    }
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            left: Val::Px(position.x),
+            top: Val::Px(position.y),
+            padding: UiRect::all(Val::Px(6.0)),
+            ..default()
+        },
+        Text(tooltip.0.clone()),
+        ThemeText::Body,
+        BackgroundColor::default(),
+        ThemeColor::Panel,
+        GlobalZIndex(TOOLTIP_Z),
+        TooltipMarker,
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slider_snaps_to_its_step() {
+        let slider = Slider::new(500.0, 100.0, 2000.0, 100.0);
+        assert_eq!(slider.value_at(0.0), 100.0);
+        assert_eq!(slider.value_at(1.0), 2000.0);
+        // 993 and 1088 before snapping
+        assert_eq!(slider.value_at(0.47), 1000.0);
+        assert_eq!(slider.value_at(0.52), 1100.0);
+    }
+
+    #[test]
+    fn slider_stays_within_its_limits() {
+        let slider = Slider::new(1.0, 0.5, 2.0, 0.4);
+        assert_eq!(slider.value_at(-1.0), 0.5);
+        assert_eq!(slider.value_at(2.0), 2.0);
+        // The last step would overshoot the max
+        assert_eq!(slider.value_at(0.99), 2.0);
+
+        let smooth = Slider::new(0.0, 0.0, 10.0, 0.0);
+        assert_eq!(smooth.value_at(0.25), 2.5);
+    }
+}
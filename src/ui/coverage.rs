@@ -0,0 +1,168 @@
+//! Defense range overlays, for the selected defense and while designing a base
+use crate::prelude::*;
+use bevy::{
+    image::ImageSampler,
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    },
+};
+
+type CoverageSource<'a> = (&'a Transform, &'a Defense);
+type DefenseMoved = Or<(Added<Defense>, Changed<GridPosition>)>;
+
+fn draw_range(gizmos: &mut Gizmos, view_mode: ViewMode, center: Vec2, range: f32, min_range: f32) {
+    let center = Isometry2d::from_translation(view_mode.project(center));
+    gizmos
+        .ellipse_2d(center, view_mode.ground_radii(range), RANGE_COLOR)
+        .resolution(64);
+    if min_range > 0.0 {
+        gizmos
+            .ellipse_2d(center, view_mode.ground_radii(min_range), MIN_RANGE_COLOR)
+            .resolution(32);
+    }
+}
+
+/// Range of the selected defense, and of the one about to be placed in the
+/// editor under the cursor, the grid cursor when it is up
+#[allow(clippy::too_many_arguments)]
+pub fn draw_defense_ranges(
+    mut gizmos: Gizmos,
+    editor_state: Option<Res<EditorState>>,
+    state: Res<State<GameState>>,
+    cursor: Option<Res<GridCursor>>,
+    windows: Query<&Window>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    view_mode: Res<ViewMode>,
+    selected: Query<(&Transform, &Defense), With<Selected>>,
+) {
+    for (transform, defense) in selected.iter() {
+        draw_range(
+            &mut gizmos,
+            *view_mode,
+            transform.translation.truncate(),
+            defense.attack_range,
+            defense.min_range,
+        );
+    }
+
+    if *state.get() != GameState::LevelEditor {
+        return;
+    }
+    let Some(BuildingType::Defense(kind)) =
+        editor_state.and_then(|editor_state| editor_state.selected_building)
+    else {
+        return;
+    };
+    let (Ok(window), Ok((camera, global_transform))) =
+        (windows.get_single(), camera_query.get_single())
+    else {
+        return;
+    };
+    let tile = cursor.and_then(|cursor| cursor.tile.map(|tile| tile.as_vec2()));
+    let Some(world) = tile.or_else(|| {
+        window
+            .cursor_position()
+            .and_then(|cursor| camera.viewport_to_world_2d(global_transform, cursor).ok())
+            .map(|world| view_mode.unproject(world))
+    }) else {
+        return;
+    };
+    if world.x < 0.0 || world.y < 0.0 {
+        return;
+    }
+
+    // Centered where the building would be placed
+    let (width, height) = to_size(BuildingType::Defense(kind));
+    let center = world.floor() + Vec2::new(width as f32, height as f32) / 2.0;
+    let (range, min_range) = defense_range(kind, 1);
+    draw_range(&mut gizmos, *view_mode, center, range, min_range);
+}
+
+/// Paints how many defenses reach each tile against the chosen attackers,
+/// uncovered tiles stand out in red. Only repainted when the toggle or a
+/// defense changes
+#[allow(clippy::too_many_arguments)]
+pub fn update_coverage_map(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    editor_state: Res<EditorState>,
+    map_query: Query<&TileMap>,
+    defenses: Query<CoverageSource>,
+    moved: Query<(), (With<Defense>, DefenseMoved)>,
+    mut removed: RemovedComponents<Defense>,
+    mut overlay: Query<(&Sprite, &mut Visibility), With<CoverageMarker>>,
+) {
+    let defenses_changed = !moved.is_empty() || removed.read().count() > 0;
+    if !editor_state.is_changed() && !defenses_changed {
+        return;
+    }
+    let Ok(map) = map_query.get_single() else {
+        return;
+    };
+
+    let Some(layer) = editor_state.coverage else {
+        for (_, mut visibility) in overlay.iter_mut() {
+            *visibility = Visibility::Hidden;
+        }
+        return;
+    };
+
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: map.width as u32,
+            height: map.height as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0; 4],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    image.sampler = ImageSampler::nearest();
+
+    // Image rows go down, map rows go up
+    for y in 0..map.height {
+        for x in 0..map.width {
+            let tile = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+            let covering = defenses
+                .iter()
+                .filter(|(transform, defense)| {
+                    if !defense.targeting.allows(layer) {
+                        return false;
+                    }
+                    let distance = transform.translation.truncate().distance(tile);
+                    distance <= defense.attack_range && distance >= defense.min_range
+                })
+                .count();
+            let color = match covering {
+                0 => UNCOVERED_COLOR,
+                n => COVERED_COLOR
+                    .with_alpha((COVERAGE_ALPHA_STEP * n as f32).min(COVERAGE_MAX_ALPHA)),
+            };
+            let idx = ((map.height - 1 - y) * map.width + x) * 4;
+            image.data[idx..idx + 4].copy_from_slice(&color.to_srgba().to_u8_array());
+        }
+    }
+
+    if let Ok((sprite, mut visibility)) = overlay.get_single_mut() {
+        images.insert(&sprite.image, image);
+        *visibility = Visibility::Inherited;
+        return;
+    }
+
+    let size = Vec2::new(map.width as f32, map.height as f32);
+    commands.spawn((
+        Sprite {
+            image: images.add(image),
+            custom_size: Some(size),
+            ..default()
+        },
+        Transform::from_translation((size / 2.0).extend(COVERAGE_Z)),
+        CoverageMarker,
+        GroundLayer,
+        // Despawned with the editor UI
+        BuildUIMarker,
+    ));
+}
@@ -1,7 +1,10 @@
 use crate::prelude::*;
 use bevy::prelude::*;
 
-pub fn setup_debug_overlay(mut commands: Commands) {
+pub fn setup_debug_overlay(mut commands: Commands, settings: Res<Settings>) {
+    commands.insert_resource(DebugState {
+        visible: settings.debug_overlay,
+    });
     commands
         .spawn((
             Node {
@@ -11,11 +14,16 @@ pub fn setup_debug_overlay(mut commands: Commands) {
                 left: Val::Px(10.0),
                 top: Val::Px(10.0),
                 padding: UiRect::all(Val::Px(10.0)),
-                display: Display::None,
+                display: if settings.debug_overlay {
+                    Display::Block
+                } else {
+                    Display::None
+                },
                 ..default()
             },
             BackgroundColor(Color::linear_rgba(0.0, 0.0, 0.0, 0.8)),
             DebugOverlayMarker,
+            GameWorldMarker,
         ))
         .with_children(|parent| {
             parent.spawn((
@@ -61,9 +69,9 @@ pub fn update_debug_overlay(
 pub fn toggle_debug_overlay(
     mut debug_state: ResMut<DebugState>,
     mut query: Query<&mut Node, With<DebugOverlayMarker>>,
-    keyboard: Res<ButtonInput<KeyCode>>,
+    actions: Res<ActionState>,
 ) {
-    if keyboard.just_pressed(KeyCode::F3) {
+    if actions.just_pressed(InputAction::ToggleDebug) {
         debug_state.visible = !debug_state.visible;
 
         for mut style in query.iter_mut() {
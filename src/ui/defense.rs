@@ -0,0 +1,102 @@
+//! Raids on the village, the heroes and clan castle troops come out to
+//! defend it
+use crate::prelude::*;
+use bevy::prelude::*;
+use std::f32::consts::TAU;
+
+type RaiderFilter = (With<Unit>, Without<Defender>);
+type StandingFilter = (With<Building>, Without<Destroyed>);
+
+pub fn setup_defense_ui(mut commands: Commands) {
+    commands
+        .spawn((whole_screen(), DefenseUIMarker))
+        .with_children(|parent| {
+            // Raid status (top left)
+            parent
+                .spawn(panel(Node {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(10.0),
+                    left: Val::Px(10.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    ..default()
+                }))
+                .with_children(|parent| {
+                    parent.spawn((text(""), RaidersLeftMarker));
+                });
+        });
+}
+
+pub fn cleanup_defense_ui(mut commands: Commands, query: Query<Entity, With<DefenseUIMarker>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Spreads the [`RAID_TROOPS`] evenly around the village, a few tiles out
+pub fn start_raid(
+    mut commands: Commands,
+    assets: Res<UnitAssets>,
+    map_query: Query<&TileMap>,
+    building_query: Query<(&GridPosition, &GridSize), StandingFilter>,
+) {
+    let Ok(map) = map_query.get_single() else {
+        return;
+    };
+    let Some((min, max)) = building_query
+        .iter()
+        .map(|(position, size)| {
+            let min = Vec2::new(position.x as f32, position.y as f32);
+            (min, min + Vec2::new(size.width as f32, size.height as f32))
+        })
+        .reduce(|(min_a, max_a), (min_b, max_b)| (min_a.min(min_b), max_a.max(max_b)))
+    else {
+        info!("Nothing to raid");
+        return;
+    };
+
+    let center = (min + max) / 2.0;
+    let radius = (max - min).length() / 2.0 + RAID_SPAWN_MARGIN;
+    let edge = Vec2::new(map.width as f32, map.height as f32) - 0.5;
+    let raiders: Vec<UnitType> = RAID_TROOPS
+        .iter()
+        .flat_map(|(unit_type, count)| std::iter::repeat_n(*unit_type, *count))
+        .collect();
+    for (i, unit_type) in raiders.iter().enumerate() {
+        let angle = TAU * i as f32 / raiders.len() as f32;
+        let position = (center + Vec2::from_angle(angle) * radius).clamp(Vec2::splat(0.5), edge);
+        deploy_unit(&mut commands, &assets, *unit_type, 1, position);
+    }
+    info!("{} raiders attack the village", raiders.len());
+}
+
+pub fn update_defense_ui(
+    raider_query: Query<(), RaiderFilter>,
+    mut text_query: Query<&mut Text, With<RaidersLeftMarker>>,
+) {
+    let content = format!("Raiders left: {}", raider_query.iter().count());
+    for mut text in text_query.iter_mut() {
+        if text.0 != content {
+            text.0.clone_from(&content);
+        }
+    }
+}
+
+/// The raid is over once every raider is down, or nothing but walls is
+/// left standing
+pub fn end_raid(
+    raider_query: Query<(), RaiderFilter>,
+    building_query: Query<&BuildingType, StandingFilter>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let standing = building_query
+        .iter()
+        .any(|building_type| *building_type != BuildingType::Wall);
+    if raider_query.is_empty() {
+        info!("The raid was held off");
+    } else if !standing {
+        info!("The raiders destroyed the village");
+    } else {
+        return;
+    }
+    next_state.set(GameState::Playing);
+}
@@ -15,86 +15,38 @@ pub fn cleanup_editor(mut commands: Commands, query: Query<Entity, With<BuildUIM
 pub fn setup_editor(mut commands: Commands) {
     // Setup editor UI
     commands
-        .spawn((
-            Node {
-                width: Val::Percent(100.0),
-                height: Val::Percent(100.0),
-                ..default()
-            },
-            BuildUIMarker,
-        ))
+        .spawn((whole_screen(), BuildUIMarker))
         .with_children(|parent| {
             // Top panel for building selection
             parent
-                .spawn((
-                    Node {
-                        width: Val::Percent(100.0),
-                        height: Val::Px(80.0),
-                        flex_direction: FlexDirection::Row,
-                        justify_content: JustifyContent::SpaceEvenly,
-                        align_items: AlignItems::Center,
-                        padding: UiRect::all(Val::Px(10.0)),
-                        ..default()
-                    },
-                    BackgroundColor(Color::linear_rgba(0.1, 0.1, 0.1, 0.8)),
-                ))
+                .spawn(toolbar(Node {
+                    width: Val::Percent(100.0),
+                    height: Val::Px(80.0),
+                    ..default()
+                }))
                 .with_children(|parent| {
-                    spawn_building_button(
-                        parent,
-                        "Town Hall",
-                        EditorButton::Building(BuildingType::TownHall),
-                    );
-                    spawn_building_button(
-                        parent,
-                        "Gold Collector",
-                        EditorButton::Building(BuildingType::Collector(ResourceType::Gold)),
-                    );
-                    spawn_building_button(
-                        parent,
-                        "Elixir Collector",
-                        EditorButton::Building(BuildingType::Collector(ResourceType::Elixir)),
-                    );
-                    spawn_building_button(
-                        parent,
-                        "Gold Storage",
-                        EditorButton::Building(BuildingType::Storage(ResourceType::Gold)),
-                    );
-                    spawn_building_button(
-                        parent,
-                        "Elixir Storage",
-                        EditorButton::Building(BuildingType::Storage(ResourceType::Elixir)),
-                    );
-                    spawn_building_button(
-                        parent,
-                        "Defense",
-                        EditorButton::Building(BuildingType::Defense),
-                    );
-                    spawn_building_button(
-                        parent,
-                        "Wall",
-                        EditorButton::Building(BuildingType::Wall),
-                    );
+                    for building in BuildingType::ALL {
+                        spawn_building_button(
+                            parent,
+                            building.name(),
+                            EditorButton::Building(building),
+                        );
+                    }
                 });
 
-            // Back button (bottom left)
+            parent
+                .spawn((corner_button(0), EditorButton::Back))
+                .with_children(|parent| {
+                    parent.spawn(text("Back"));
+                });
             parent
                 .spawn((
-                    Node {
-                        width: Val::Px(120.0),
-                        height: Val::Px(50.0),
-                        position_type: PositionType::Absolute,
-                        bottom: Val::Px(20.0),
-                        left: Val::Px(20.0),
-                        justify_content: JustifyContent::Center,
-                        align_items: AlignItems::Center,
-                        ..default()
-                    },
-                    Button,
-                    ColorPalette::new_with_bg(BLUE_6, BLUE_4, BLUE_2),
-                    EditorButton::Back,
+                    corner_button(1),
+                    EditorButton::Coverage,
+                    Tooltip("How many defenses reach each tile, against ground then air".into()),
                 ))
                 .with_children(|parent| {
-                    parent.spawn(Text("Back".into()));
+                    parent.spawn((text(coverage_label(None)), CoverageLabelMarker));
                 });
         });
 
@@ -102,11 +54,19 @@ pub fn setup_editor(mut commands: Commands) {
     commands.insert_resource(EditorState::default());
 }
 
+fn coverage_label(coverage: Option<UnitLayer>) -> &'static str {
+    match coverage {
+        None => "Coverage",
+        Some(UnitLayer::Ground) => "Ground",
+        Some(UnitLayer::Air) => "Air",
+    }
+}
+
 fn spawn_building_button(parent: &mut ChildBuilder, name: &str, button_type: EditorButton) {
     parent
         .spawn((standard_button(), button_type))
         .with_children(|parent| {
-            parent.spawn(Text(name.into()));
+            parent.spawn(text(name));
         });
 }
 
@@ -115,12 +75,26 @@ pub fn editor_button_handler(
     mut events: EventReader<ButtonInteractionEvent<EditorButton>>,
     mut next_state: ResMut<NextState<GameState>>,
     mut editor_state: ResMut<EditorState>,
+    start: Res<SessionStart>,
+    mut coverage_label_query: Query<&mut Text, With<CoverageLabelMarker>>,
 ) {
     for event in events.read() {
         match event {
             ButtonInteractionEvent::Pressed(button) => match button {
-                EditorButton::Back => {
-                    next_state.set(GameState::Playing);
+                // The sandbox has no village to go back to
+                EditorButton::Back => match *start {
+                    SessionStart::Sandbox => next_state.set(GameState::MainMenu),
+                    _ => next_state.set(GameState::Playing),
+                },
+                EditorButton::Coverage => {
+                    editor_state.coverage = match editor_state.coverage {
+                        None => Some(UnitLayer::Ground),
+                        Some(UnitLayer::Ground) => Some(UnitLayer::Air),
+                        Some(UnitLayer::Air) => None,
+                    };
+                    for mut text in coverage_label_query.iter_mut() {
+                        text.0 = coverage_label(editor_state.coverage).into();
+                    }
                 }
                 EditorButton::Building(building) => {
                     editor_state.selected_building = Some(*building);
@@ -135,46 +109,79 @@ pub fn editor_button_handler(
     }
 }
 
+/// Places the selected building on a click, drags only move the camera
 pub fn place_editor_building(
     mut commands: Commands,
-    buttons: Res<ButtonInput<MouseButton>>,
-    windows: Query<&Window>,
-    camera_q: Query<(&Camera, &GlobalTransform)>,
+    mut clicks: EventReader<WorldClick>,
     mut map_query: Query<&mut TileMap>,
     mut editor_state: ResMut<EditorState>,
     assets: Res<BuildingAssets>,
 ) {
-    if buttons.just_pressed(MouseButton::Left) {
+    for click in clicks.read() {
         if editor_state.is_selected {
-            return;
+            continue;
+        }
+        let Some(building_type) = editor_state.selected_building else {
+            continue;
+        };
+        if click.position.x < 0.0 || click.position.y < 0.0 {
+            continue;
         }
-        if let Some(building_type) = &editor_state.selected_building {
-            // Get cursor position in world
-            let (camera, camera_transform) = camera_q.single();
-            let window = windows.single();
 
-            if let Some(cursor_pos) = window.cursor_position() {
-                if let Ok(world_pos) = camera.viewport_to_world_2d(camera_transform, cursor_pos) {
-                    // Convert to grid coordinates
-                    let grid_x = world_pos.x.floor() as usize;
-                    let grid_y = world_pos.y.floor() as usize;
+        // Convert to grid coordinates
+        let grid_x = click.position.x.floor() as usize;
+        let grid_y = click.position.y.floor() as usize;
 
-                    if let Ok(mut map) = map_query.get_single_mut() {
-                        if map.can_place(grid_x, grid_y, to_size(*building_type)) {
-                            place_building(
-                                &mut commands,
-                                &assets,
-                                *building_type,
-                                1,
-                                grid_x,
-                                grid_y,
-                            );
-                            info!("Placed {:?} at ({}, {})", building_type, grid_x, grid_y);
-                            editor_state.selected_building = None;
-                        }
-                    }
-                }
+        if let Ok(mut map) = map_query.get_single_mut() {
+            if map.can_place(grid_x, grid_y, to_size(building_type)) {
+                let entity =
+                    place_building(&mut commands, &assets, building_type, 1, grid_x, grid_y);
+                editor_state.history.push(entity);
+                info!("Placed {:?} at ({}, {})", building_type, grid_x, grid_y);
+                editor_state.selected_building = None;
             }
         }
     }
 }
+
+/// Cancel drops the building picked for placement, the next building one
+/// picks another, undo takes back the last one placed
+pub fn editor_actions(
+    mut commands: Commands,
+    actions: Res<ActionState>,
+    mut editor_state: ResMut<EditorState>,
+    mut map_query: Query<&mut TileMap>,
+    placed: Query<(&BuildingType, &GridPosition)>,
+) {
+    if actions.just_pressed(InputAction::Cancel) {
+        editor_state.selected_building = None;
+    }
+    if actions.just_pressed(InputAction::CycleBuilding) {
+        let next = match editor_state.selected_building {
+            Some(current) => BuildingType::ALL
+                .iter()
+                .position(|building| *building == current)
+                .map_or(0, |idx| (idx + 1) % BuildingType::ALL.len()),
+            None => 0,
+        };
+        editor_state.selected_building = Some(BuildingType::ALL[next]);
+        info!("Placing {:?}", BuildingType::ALL[next]);
+    }
+    if !actions.just_pressed(InputAction::Undo) {
+        return;
+    }
+    let Some(entity) = editor_state.history.pop() else {
+        return;
+    };
+    let Ok((building_type, position)) = placed.get(entity) else {
+        return;
+    };
+    let Ok(mut map) = map_query.get_single_mut() else {
+        return;
+    };
+    remove_building(&mut commands, &mut map, entity, *building_type, position);
+    info!(
+        "Undid {building_type:?} at ({}, {})",
+        position.x, position.y
+    );
+}
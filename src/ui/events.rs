@@ -16,7 +16,7 @@ type QueryTuple<'a, T: Component + Copy> = (
     &'a ColorPalette,
     &'a mut BackgroundColor,
 );
-type Filters = (Changed<Interaction>, With<Button>);
+type Filters = (Changed<Interaction>, With<Button>, Without<Disabled>);
 pub fn handle_button_interactions<T: Component + Copy>(
     mut button_query: Query<QueryTuple<T>, Filters>,
     mut interaction_events: EventWriter<ButtonInteractionEvent<T>>,
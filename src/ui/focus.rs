@@ -0,0 +1,125 @@
+//! Keyboard and gamepad navigation between the buttons of a menu
+use crate::prelude::*;
+use bevy::prelude::*;
+
+type FocusCandidate<'a> = (
+    Entity,
+    &'a GlobalTransform,
+    &'a ComputedNode,
+    &'a InheritedVisibility,
+);
+type FocusFilter = (With<Button>, Without<Disabled>);
+type FocusScopeRank<'a> = (Entity, Option<&'a GlobalZIndex>);
+
+/// Buttons under the topmost [`FocusScope`], or all of them in game where
+/// there is none, top to bottom then left to right. Dialogs are roots like
+/// the screens under them, so scopes rank by [`GlobalZIndex`] before depth
+fn focus_order(
+    scopes: &Query<FocusScopeRank, With<FocusScope>>,
+    parents: &Query<&Parent>,
+    children: &Query<&Children>,
+    buttons: &Query<FocusCandidate, FocusFilter>,
+) -> Vec<Entity> {
+    let depth = |entity: Entity| parents.iter_ancestors(entity).count();
+    let topmost = scopes
+        .iter()
+        .max_by_key(|(scope, z_index)| (z_index.map_or(0, |z_index| z_index.0), depth(*scope)));
+    let candidates: Vec<_> = match topmost {
+        Some((scope, _)) => children.iter_descendants(scope).collect(),
+        None => buttons.iter().map(|(entity, ..)| entity).collect(),
+    };
+
+    let mut order: Vec<_> = candidates
+        .into_iter()
+        .filter_map(|entity| buttons.get(entity).ok())
+        .filter(|(_, _, node, visibility)| visibility.get() && node.size() != Vec2::ZERO)
+        .map(|(entity, transform, _, _)| (entity, transform.translation().truncate()))
+        .collect();
+    order.sort_by(|(_, a), (_, b)| a.y.total_cmp(&b.y).then(a.x.total_cmp(&b.x)));
+    order.into_iter().map(|(entity, _)| entity).collect()
+}
+
+/// Arrows, Tab or the d-pad move the focus, Enter, Space or the south button
+/// press the focused button. In game the arrows, d-pad and south button
+/// belong to the map, Tab and the bumpers move the focus and the north
+/// button presses
+#[allow(clippy::too_many_arguments)]
+pub fn navigate_focus(
+    mut commands: Commands,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    state: Res<State<GameState>>,
+    mut focus: ResMut<UiFocus>,
+    rebinding: Res<Rebinding>,
+    mut pressed: Local<Option<Entity>>,
+    scopes: Query<FocusScopeRank, With<FocusScope>>,
+    parents: Query<&Parent>,
+    children: Query<&Children>,
+    buttons: Query<FocusCandidate, FocusFilter>,
+    mut interactions: Query<&mut Interaction>,
+) {
+    // Let go of the press from last frame, like a mouse release would
+    if let Some(mut interaction) = pressed.take().and_then(|e| interactions.get_mut(e).ok()) {
+        *interaction = Interaction::None;
+    }
+    // Keys go to the binding being changed
+    if rebinding.0.is_some() {
+        return;
+    }
+
+    let order = focus_order(&scopes, &parents, &children, &buttons);
+    let current = focus
+        .focused
+        .and_then(|focused| order.iter().position(|entity| *entity == focused));
+
+    let any_gamepad = |button: GamepadButton| gamepads.iter().any(|pad| pad.just_pressed(button));
+    let in_menu = !matches!(
+        state.get(),
+        GameState::Playing | GameState::Attack | GameState::Defense | GameState::LevelEditor
+    );
+    let shift = keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let previous = (shift && keyboard.just_pressed(KeyCode::Tab))
+        || any_gamepad(GamepadButton::LeftTrigger)
+        || (in_menu
+            && (keyboard.any_just_pressed([KeyCode::ArrowUp, KeyCode::ArrowLeft])
+                || any_gamepad(GamepadButton::DPadUp)
+                || any_gamepad(GamepadButton::DPadLeft)));
+    let next = (!shift && keyboard.just_pressed(KeyCode::Tab))
+        || any_gamepad(GamepadButton::RightTrigger)
+        || (in_menu
+            && (keyboard.any_just_pressed([KeyCode::ArrowDown, KeyCode::ArrowRight])
+                || any_gamepad(GamepadButton::DPadDown)
+                || any_gamepad(GamepadButton::DPadRight)));
+    let confirm = keyboard.just_pressed(KeyCode::Enter)
+        || any_gamepad(GamepadButton::North)
+        || (in_menu
+            && (keyboard.just_pressed(KeyCode::Space) || any_gamepad(GamepadButton::South)));
+
+    let target = match (current, order.len()) {
+        (_, 0) => None,
+        (None, _) if previous || next => Some(order[0]),
+        (Some(idx), len) if previous => Some(order[(idx + len - 1) % len]),
+        (Some(idx), len) if next => Some(order[(idx + 1) % len]),
+        (Some(idx), _) => Some(order[idx]),
+        (None, _) => None,
+    };
+
+    if target != focus.focused {
+        if let Some(old) = focus.focused.filter(|old| buttons.contains(*old)) {
+            commands.entity(old).remove::<Outline>();
+        }
+        if let Some(new) = target {
+            commands
+                .entity(new)
+                .insert(Outline::new(FOCUS_OUTLINE, Val::ZERO, FOCUS_COLOR));
+        }
+        focus.focused = target;
+    }
+
+    if confirm {
+        if let Some(mut interaction) = target.and_then(|e| interactions.get_mut(e).ok()) {
+            *interaction = Interaction::Pressed;
+            *pressed = target;
+        }
+    }
+}
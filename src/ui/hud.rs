@@ -1,36 +1,30 @@
 use crate::prelude::*;
 use bevy::prelude::*;
 
+/// The session has a village, the sandbox only gets the editor
+pub fn in_village(start: Res<SessionStart>) -> bool {
+    !matches!(*start, SessionStart::Sandbox)
+}
+
 pub fn setup_hud(mut commands: Commands) {
     commands
         .spawn((whole_screen(), GameHUDMarker))
         .with_children(|parent| {
             // Resources panel (top right)
             parent
-                .spawn((
-                    Node {
-                        width: Val::Auto,
-                        height: Val::Auto,
-                        position_type: PositionType::Absolute,
-                        top: Val::Px(10.0),
-                        right: Val::Px(10.0),
-                        padding: UiRect::all(Val::Px(10.0)),
-                        ..default()
-                    },
-                    BackgroundColor(Color::linear_rgba(0.1, 0.1, 0.1, 0.7)),
-                    ResourceDisplayMarker,
-                ))
+                .spawn(panel(Node {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(10.0),
+                    right: Val::Px(10.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    ..default()
+                }))
                 .with_children(|parent| {
-                    parent.spawn(Text("Resources:".into()));
-                    parent.spawn((
-                        Text("".into()), // Will be updated by the system
-                        ResourceDisplayMarker,
-                    ));
+                    parent.spawn(text("Resources:"));
+                    // Will be updated by the system
+                    parent.spawn((text(""), ResourceDisplayMarker));
                 });
         });
-
-    // Insert debug state resource
-    commands.insert_resource(DebugState::default());
 }
 
 pub fn update_resource_display(
@@ -47,26 +41,138 @@ pub fn update_resource_display(
 
 pub fn setup_lower_ui(mut commands: Commands) {
     commands
-        .spawn((whole_screen(), GameHUDMarker))
+        .spawn((whole_screen(), GameHUDMarker, LowerHUDMarker))
         .with_children(|parent| {
-            // Build button (bottom right)
+            // Action buttons (bottom right)
             parent
-                .spawn((
-                    Button,
-                    Node {
-                        width: Val::Auto,
-                        height: Val::Auto,
-                        position_type: PositionType::Absolute,
-                        bottom: Val::Px(10.0),
-                        right: Val::Px(10.0),
-                        padding: UiRect::all(Val::Px(10.0)),
-                        ..default()
-                    },
-                    ColorPalette::new_with_bg(BROWN_4, BROWN_3, BROWN_2),
-                    MenuButton::Editor,
-                ))
+                .spawn(toolbar(Node {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(10.0),
+                    right: Val::Px(10.0),
+                    column_gap: Val::Px(10.0),
+                    ..default()
+                }))
                 .with_children(|parent| {
-                    parent.spawn(Text("Editor".into()));
+                    parent
+                        .spawn(Node {
+                            flex_direction: FlexDirection::Column,
+                            justify_content: JustifyContent::Center,
+                            row_gap: Val::Px(4.0),
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            parent.spawn(text("Brewing"));
+                            spawn_progress_bar(parent, Val::Px(100.0), BrewProgressMarker);
+                        });
+                    spawn_lower_button(parent, "Rearm Traps", HudButton::RearmTraps)
+                        .insert(Tooltip("Rearms the traps sprung in battle".into()));
+                    for spell in SpellType::ALL {
+                        let name = format!("Brew {}", spell.name());
+                        spawn_lower_button(parent, &name, HudButton::BrewSpell(spell)).insert(
+                            Tooltip(format!(
+                                "{} elixir, {:.0}s",
+                                spell.cost(),
+                                spell.brew_time()
+                            )),
+                        );
+                    }
+                    for hero in HeroType::ALL {
+                        let name = format!("Upgrade {}", hero.name());
+                        spawn_lower_button(parent, &name, HudButton::UpgradeHero(hero));
+                    }
+                    spawn_lower_button(parent, "Request Troops", HudButton::RequestTroops)
+                        .insert(Tooltip("Asks the clan for troops to defend".into()));
+                    spawn_lower_button(parent, "Defend", MenuButton::Defend).insert(Tooltip(
+                        "Raiders attack, heroes and clan troops defend".into(),
+                    ));
+                    spawn_lower_button(parent, "Attack", MenuButton::Attack);
+                    spawn_lower_button(parent, "Editor", MenuButton::Editor);
                 });
         });
 }
+
+/// The village actions can't be used in a battle or the editor
+pub fn show_lower_hud(
+    state: Res<State<GameState>>,
+    mut query: Query<&mut Visibility, With<LowerHUDMarker>>,
+) {
+    let target = if *state.get() == GameState::Playing {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+    for mut visibility in query.iter_mut() {
+        visibility.set_if_neq(target);
+    }
+}
+
+fn spawn_lower_button<'a>(
+    parent: &'a mut ChildBuilder,
+    name: &str,
+    button_type: impl Component,
+) -> EntityCommands<'a> {
+    let mut entity = parent.spawn((compact_button(), button_type));
+    entity.with_children(|parent| {
+        parent.spawn(text(name));
+    });
+    entity
+}
+
+/// Fills the brewing bar with the furthest along of the factories
+pub fn update_brew_progress(
+    factories: Query<&SpellFactory>,
+    mut bars: Query<&mut ProgressBar, With<BrewProgressMarker>>,
+) {
+    let progress = factories
+        .iter()
+        .filter_map(|factory| {
+            let spell = factory.queue.first()?;
+            Some(factory.progress / spell.brew_time())
+        })
+        .fold(0.0, f32::max);
+    for mut bar in bars.iter_mut() {
+        if bar.0 != progress {
+            bar.0 = progress;
+        }
+    }
+}
+
+/// Triggered bv [`handle_button_interactions`]
+pub fn hud_button_handler(
+    mut events: EventReader<ButtonInteractionEvent<HudButton>>,
+    mut resources: ResMut<PlayerResources>,
+    mut traps: Query<&mut Trap>,
+    inventory: Res<SpellInventory>,
+    mut factories: Query<&mut SpellFactory>,
+    mut altars: Query<&mut HeroAltar>,
+    mut clan: ResMut<Clan>,
+) {
+    for event in events.read() {
+        if let ButtonInteractionEvent::Pressed(button) = event {
+            match button {
+                HudButton::RearmTraps => {
+                    let rearmed = rearm_traps(&mut resources, &mut traps);
+                    info!("Rearmed {rearmed} traps");
+                }
+                HudButton::BrewSpell(spell) => {
+                    if queue_spell(*spell, &mut resources, &inventory, &mut factories) {
+                        info!("Queued {spell:?}");
+                    } else {
+                        info!("Can't brew {spell:?}, no elixir or factory space");
+                    }
+                }
+                HudButton::UpgradeHero(hero) => {
+                    if upgrade_hero(*hero, &mut resources, &mut altars) {
+                        info!("Upgraded {hero:?}");
+                    } else {
+                        info!("Can't upgrade {hero:?}, no altar, elixir or max level");
+                    }
+                }
+                HudButton::RequestTroops => {
+                    clan.request();
+                    info!("Requested troops from {}", clan.name);
+                }
+            }
+        }
+    }
+}
@@ -1,19 +1,67 @@
 use crate::prelude::*;
 use bevy::prelude::*;
+use std::path::Path;
 
 pub fn setup_menu(mut commands: Commands, _asset_server: Res<AssetServer>) {
+    let has_save = Path::new(SAVE_PATH).exists();
     commands
-        .spawn((whole_screen_center(), MainMenuMarker))
+        .spawn((whole_screen_center(), MainMenuMarker, FocusScope))
         .with_children(|parent| {
             parent.spawn(title_text("Clash-like Game"));
             parent
-                .spawn((standard_button(), MenuButton::Play))
+                .spawn(Node {
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(10.0),
+                    ..default()
+                })
                 .with_children(|parent| {
-                    parent.spawn(big_text("Play"));
+                    let mut continue_button =
+                        spawn_menu_button(parent, "Continue", MenuButton::Continue);
+                    if !has_save {
+                        continue_button.insert(Disabled);
+                    }
+                    spawn_menu_button(parent, "New Game", MenuButton::NewGame);
+                    spawn_menu_button(parent, "Level Editor", MenuButton::Sandbox);
+                    spawn_menu_button(parent, "Settings", MenuButton::Settings);
+                    spawn_menu_button(parent, "Quit", MenuButton::Quit);
                 });
         });
 }
 
+fn spawn_menu_button<'a>(
+    parent: &'a mut ChildBuilder,
+    name: &str,
+    button: MenuButton,
+) -> EntityCommands<'a> {
+    let mut entity = parent.spawn((standard_button(), button));
+    entity.with_children(|parent| {
+        parent.spawn(big_text(name));
+    });
+    entity
+}
+
+/// Asks before a new game replaces the saved village
+fn spawn_confirm_dialog(commands: &mut Commands, menu: Entity) {
+    let dialog = spawn_modal(commands, 1, "Start over?", |parent| {
+        parent.spawn(text("The saved village will be lost"));
+        parent
+            .spawn(Node {
+                flex_direction: FlexDirection::Row,
+                justify_content: JustifyContent::Center,
+                column_gap: Val::Px(10.0),
+                ..default()
+            })
+            .with_children(|parent| {
+                spawn_menu_button(parent, "Start", MenuButton::ConfirmNewGame);
+                spawn_menu_button(parent, "Cancel", MenuButton::CancelNewGame);
+            });
+    });
+    commands
+        .entity(dialog)
+        .insert(ConfirmDialogMarker)
+        .set_parent(menu);
+}
+
 pub fn cleanup_menu(mut commands: Commands, query: Query<Entity, With<MainMenuMarker>>) {
     info!(
         "Doing menu cleanup of: {} items",
@@ -26,15 +74,58 @@ pub fn cleanup_menu(mut commands: Commands, query: Query<Entity, With<MainMenuMa
 }
 
 /// Triggered bv [`handle_button_interactions`]
+#[allow(clippy::too_many_arguments)]
 pub fn menu_button_handler(
+    mut commands: Commands,
     mut events: EventReader<ButtonInteractionEvent<MenuButton>>,
     mut next_state: ResMut<NextState<GameState>>,
+    menu: Query<Entity, With<MainMenuMarker>>,
+    dialogs: Query<Entity, With<ConfirmDialogMarker>>,
+    settings_menu: Query<Entity, With<SettingsMenuMarker>>,
+    settings: Res<Settings>,
+    state: Res<State<GameState>>,
 ) {
+    let in_village = *state.get() == GameState::Playing;
     for event in events.read() {
         if let ButtonInteractionEvent::Pressed(button) = event {
             match button {
-                MenuButton::Play => next_state.set(GameState::Playing),
+                MenuButton::Continue => match SaveGame::read(SAVE_PATH) {
+                    Ok(save) => {
+                        commands.insert_resource(SessionStart::Continue(save));
+                        next_state.set(GameState::Playing);
+                    }
+                    Err(err) => error!("Couldn't load {SAVE_PATH}: {err}"),
+                },
+                MenuButton::NewGame => {
+                    if !Path::new(SAVE_PATH).exists() {
+                        commands.insert_resource(SessionStart::NewGame);
+                        next_state.set(GameState::Playing);
+                    } else if let (Ok(menu), true) = (menu.get_single(), dialogs.is_empty()) {
+                        spawn_confirm_dialog(&mut commands, menu);
+                    }
+                }
+                MenuButton::ConfirmNewGame => {
+                    if let Err(err) = std::fs::remove_file(SAVE_PATH) {
+                        error!("Couldn't remove {SAVE_PATH}: {err}");
+                    }
+                    commands.insert_resource(SessionStart::NewGame);
+                    next_state.set(GameState::Playing);
+                }
+                MenuButton::CancelNewGame => {
+                    for dialog in dialogs.iter() {
+                        commands.entity(dialog).despawn_recursive();
+                    }
+                }
+                MenuButton::Sandbox => {
+                    commands.insert_resource(SessionStart::Sandbox);
+                    next_state.set(GameState::LevelEditor);
+                }
+                // The HUD buttons only leave the village
+                MenuButton::Editor | MenuButton::Attack | MenuButton::Defend if !in_village => (),
                 MenuButton::Editor => next_state.set(GameState::LevelEditor),
+                MenuButton::Attack => next_state.set(GameState::Attack),
+                MenuButton::Defend => next_state.set(GameState::Defense),
+                MenuButton::Settings => open_settings(&mut commands, &settings, &settings_menu),
                 MenuButton::Quit => std::process::exit(0),
             }
         }
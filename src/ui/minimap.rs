@@ -0,0 +1,199 @@
+//! Minimap of the village, click it to move the camera there
+use crate::prelude::*;
+use bevy::{
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    },
+    ui::RelativeCursorPosition,
+};
+
+pub fn setup_minimap(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    map_query: Query<&TileMap>,
+) {
+    let map = map_query.single();
+    let image = Image::new_fill(
+        Extent3d {
+            width: map.width as u32,
+            height: map.height as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &MINIMAP_BACKGROUND.to_srgba().to_u8_array(),
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+
+    commands
+        .spawn((
+            Node {
+                width: Val::Px(MINIMAP_SIZE),
+                height: Val::Px(MINIMAP_SIZE),
+                position_type: PositionType::Absolute,
+                top: Val::Px(90.0),
+                left: Val::Px(10.0),
+                border: UiRect::all(Val::Px(2.0)),
+                overflow: Overflow::clip(),
+                ..default()
+            },
+            BorderColor::default(),
+            ThemeBorder::Frame,
+            ImageNode::new(images.add(image)),
+            Interaction::default(),
+            RelativeCursorPosition::default(),
+            MinimapMarker,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Node {
+                    position_type: PositionType::Absolute,
+                    border: UiRect::all(Val::Px(1.0)),
+                    ..default()
+                },
+                BorderColor::default(),
+                ThemeBorder::Accent,
+                MinimapViewportMarker,
+            ));
+        });
+}
+
+/// Paints the map occupancy, and the units during battles. Refreshes a few
+/// times per second, and only touches the image when a pixel changed
+#[allow(clippy::too_many_arguments)]
+pub fn update_minimap(
+    time: Res<Time>,
+    mut elapsed: Local<f32>,
+    mut frame: Local<Vec<u8>>,
+    state: Res<State<GameState>>,
+    mut images: ResMut<Assets<Image>>,
+    materials: Res<Assets<ColorMaterial>>,
+    assets: Res<BuildingAssets>,
+    map_query: Query<&TileMap>,
+    minimap_query: Query<&ImageNode, With<MinimapMarker>>,
+    building_query: Query<(&BuildingType, Option<&Trap>)>,
+    unit_query: Query<(&Transform, Has<Defender>), With<Unit>>,
+) {
+    *elapsed += time.delta_secs();
+    if *elapsed < MINIMAP_REFRESH {
+        return;
+    }
+    *elapsed = 0.0;
+
+    let (Ok(map), Ok(minimap)) = (map_query.get_single(), minimap_query.get_single()) else {
+        return;
+    };
+    let attacking = *state.get() == GameState::Attack;
+    let in_battle = matches!(state.get(), GameState::Attack | GameState::Defense);
+    let color_of = |building_type: &BuildingType| {
+        materials
+            .get(&assets.get(building_type).color)
+            .map_or(WHITE, |material| material.color)
+    };
+
+    // Image rows go down, map rows go up
+    frame.resize(map.width * map.height * 4, 0);
+    let mut paint = |x: usize, y: usize, color: Color| {
+        if x < map.width && y < map.height {
+            let idx = ((map.height - 1 - y) * map.width + x) * 4;
+            frame[idx..idx + 4].copy_from_slice(&color.to_srgba().to_u8_array());
+        }
+    };
+
+    for y in 0..map.height {
+        for x in 0..map.width {
+            let color = map
+                .get_entity_at(x, y)
+                .and_then(|entity| building_query.get(entity).ok())
+                // Hidden traps stay hidden on the minimap too
+                .filter(|(_, trap)| !(attacking && trap.is_some_and(|trap| trap.armed)))
+                .map_or(MINIMAP_BACKGROUND, |(building_type, _)| {
+                    color_of(building_type)
+                });
+            paint(x, y, color);
+        }
+    }
+
+    if in_battle {
+        for (transform, is_defender) in unit_query.iter() {
+            let position = transform.translation;
+            if position.x < 0.0 || position.y < 0.0 {
+                continue;
+            }
+            let color = if is_defender {
+                MINIMAP_DEFENDER_COLOR
+            } else {
+                MINIMAP_ATTACKER_COLOR
+            };
+            paint(position.x as usize, position.y as usize, color);
+        }
+    }
+
+    // Touching the image uploads it again, leave it alone when nothing moved
+    if images
+        .get(&minimap.image)
+        .is_none_or(|image| image.data == *frame)
+    {
+        return;
+    }
+    let Some(image) = images.get_mut(&minimap.image) else {
+        return;
+    };
+    for (pixel, painted) in image.data.chunks_exact_mut(4).zip(frame.chunks_exact(4)) {
+        if pixel != painted {
+            pixel.copy_from_slice(painted);
+        }
+    }
+}
+
+/// Moves the frame showing what the camera sees
+pub fn update_minimap_viewport(
+    windows: Query<&Window>,
+    map_query: Query<&TileMap>,
+    camera_query: Query<(&Transform, &OrthographicProjection), With<GameCamera>>,
+    mut viewport_query: Query<&mut Node, With<MinimapViewportMarker>>,
+    view_mode: Res<ViewMode>,
+) {
+    let (Ok(map), Ok(mut node)) = (map_query.get_single(), viewport_query.get_single_mut()) else {
+        return;
+    };
+    let window = windows.single();
+    let (transform, projection) = camera_query.single();
+
+    let size = Vec2::new(map.width as f32, map.height as f32);
+    let half_extent = view_half_extent(*view_mode, window.size() / 2.0 * projection.scale);
+    let center = view_mode.unproject(transform.translation.truncate());
+
+    node.left = Val::Percent((center.x - half_extent.x) / size.x * 100.0);
+    node.top = Val::Percent((size.y - center.y - half_extent.y) / size.y * 100.0);
+    node.width = Val::Percent(half_extent.x * 2.0 / size.x * 100.0);
+    node.height = Val::Percent(half_extent.y * 2.0 / size.y * 100.0);
+}
+
+/// Pressing, or dragging over, the minimap recenters the camera
+pub fn minimap_click(
+    minimap_query: Query<(&Interaction, &RelativeCursorPosition), With<MinimapMarker>>,
+    map_query: Query<&TileMap>,
+    mut camera_query: Query<&mut GameCamera>,
+    view_mode: Res<ViewMode>,
+) {
+    let (Ok((interaction, cursor)), Ok(map)) = (minimap_query.get_single(), map_query.get_single())
+    else {
+        return;
+    };
+    if *interaction != Interaction::Pressed {
+        return;
+    }
+    let Some(normalized) = cursor.normalized.filter(|_| cursor.mouse_over()) else {
+        return;
+    };
+
+    let mut camera = camera_query.single_mut();
+    camera.target = view_mode.project(Vec2::new(
+        normalized.x * map.width as f32,
+        (1.0 - normalized.y) * map.height as f32,
+    ));
+    camera.zoom_anchor = None;
+}
@@ -0,0 +1,131 @@
+//! Escape pauses the game: virtual time stops and the state the game was in
+//! is kept as is, to be resumed later
+use crate::prelude::*;
+use bevy::{prelude::*, state::state::StateTransitionEvent};
+
+/// The transition being applied leads into the pause menu, systems tearing
+/// down a state skip it
+pub fn pausing(mut transitions: EventReader<StateTransitionEvent<GameState>>) -> bool {
+    transitions
+        .read()
+        .last()
+        .is_some_and(|transition| transition.entered == Some(GameState::Paused))
+}
+
+/// The transition being applied leaves the pause menu, systems setting up a
+/// state skip it
+pub fn resuming(mut transitions: EventReader<StateTransitionEvent<GameState>>) -> bool {
+    transitions
+        .read()
+        .last()
+        .is_some_and(|transition| transition.exited == Some(GameState::Paused))
+}
+
+pub fn toggle_pause(
+    mut commands: Commands,
+    actions: Res<ActionState>,
+    rebinding: Res<Rebinding>,
+    state: Res<State<GameState>>,
+    pause: Option<Res<PauseState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    // The key goes to the binding being changed
+    if rebinding.0.is_some() || !actions.just_pressed(InputAction::Pause) {
+        return;
+    }
+    match state.get() {
+        GameState::Paused => {
+            if let Some(pause) = pause {
+                next_state.set(pause.resume_to.clone());
+            }
+        }
+        GameState::Playing | GameState::Attack | GameState::Defense | GameState::LevelEditor => {
+            commands.insert_resource(PauseState {
+                resume_to: state.get().clone(),
+            });
+            next_state.set(GameState::Paused);
+        }
+        GameState::MainMenu => (),
+    }
+}
+
+pub fn setup_pause_menu(mut commands: Commands, mut time: ResMut<Time<Virtual>>) {
+    time.pause();
+    commands
+        .spawn((modal_overlay(0), PauseMenuMarker))
+        .with_children(|parent| {
+            parent.spawn(title_text("Paused"));
+            parent
+                .spawn(Node {
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(10.0),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for (name, button) in [
+                        ("Resume", PauseButton::Resume),
+                        ("Settings", PauseButton::Settings),
+                        ("Save", PauseButton::Save),
+                        ("Main Menu", PauseButton::MainMenu),
+                        ("Quit", PauseButton::Quit),
+                    ] {
+                        parent
+                            .spawn((standard_button(), button))
+                            .with_children(|parent| {
+                                parent.spawn(text(name));
+                            });
+                    }
+                });
+        });
+}
+
+pub fn cleanup_pause_menu(
+    mut commands: Commands,
+    mut time: ResMut<Time<Virtual>>,
+    query: Query<Entity, With<PauseMenuMarker>>,
+) {
+    time.unpause();
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Triggered bv [`handle_button_interactions`]
+#[allow(clippy::too_many_arguments)]
+pub fn pause_button_handler(
+    mut commands: Commands,
+    settings_menu: Query<Entity, With<SettingsMenuMarker>>,
+    settings: Res<Settings>,
+    mut events: EventReader<ButtonInteractionEvent<PauseButton>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    pause: Res<PauseState>,
+    start: Res<SessionStart>,
+    resources: Option<Res<PlayerResources>>,
+    buildings: Query<SaveTarget, SaveFilter>,
+) {
+    for event in events.read() {
+        if let ButtonInteractionEvent::Pressed(button) = event {
+            match button {
+                PauseButton::Resume => next_state.set(pause.resume_to.clone()),
+                PauseButton::Settings => open_settings(&mut commands, &settings, &settings_menu),
+                PauseButton::Save => {
+                    // Buildings are destroyed mid-battle, the layout is only
+                    // saved from the village, never from the sandbox
+                    let Some(resources) = resources.as_ref().filter(|_| {
+                        !matches!(pause.resume_to, GameState::Attack | GameState::Defense)
+                            && !matches!(*start, SessionStart::Sandbox)
+                    }) else {
+                        info!("Nothing to save here");
+                        continue;
+                    };
+                    match save_village(resources, &buildings) {
+                        Ok(count) => info!("Saved {count} buildings to {SAVE_PATH}"),
+                        Err(err) => error!("Couldn't save to {SAVE_PATH}: {err}"),
+                    }
+                }
+                PauseButton::MainMenu => next_state.set(GameState::MainMenu),
+                PauseButton::Quit => std::process::exit(0),
+            }
+        }
+    }
+}
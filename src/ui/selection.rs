@@ -0,0 +1,305 @@
+//! Clicking a building in the village selects it and shows its info panel
+use crate::prelude::*;
+use bevy::prelude::*;
+
+type InfoTarget<'a> = (
+    &'a BuildingType,
+    Option<&'a Building>,
+    Option<&'a ResourceCollector>,
+    Option<&'a Storage>,
+    Option<&'a Defense>,
+    Option<&'a Trap>,
+    Option<&'a SpellFactory>,
+    Option<&'a ClanCastle>,
+);
+
+type SelectedBuilding<'a> = (
+    Entity,
+    &'a BuildingType,
+    Option<&'a Building>,
+    &'a GridPosition,
+    Option<&'a mut ResourceCollector>,
+);
+/// Traps have no [`Building`] but can be moved and removed all the same
+type SelectableFilter = Or<(With<Building>, With<Trap>)>;
+
+pub fn setup_info_panel(mut commands: Commands) {
+    commands.insert_resource(SelectionState::default());
+    commands
+        .spawn((
+            panel(Node {
+                width: Val::Px(320.0),
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(70.0),
+                left: Val::Px(10.0),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(10.0),
+                padding: UiRect::all(Val::Px(10.0)),
+                display: Display::None,
+                ..default()
+            }),
+            Interaction::default(),
+            GameHUDMarker,
+            InfoPanelMarker,
+        ))
+        .with_children(|parent| {
+            parent.spawn((text(""), InfoTextMarker));
+            parent
+                .spawn(Node {
+                    flex_direction: FlexDirection::Row,
+                    column_gap: Val::Px(10.0),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for (name, button) in [
+                        ("Upgrade", InfoButton::Upgrade),
+                        ("Collect", InfoButton::Collect),
+                        ("Move", InfoButton::Move),
+                        ("Remove", InfoButton::Remove),
+                    ] {
+                        parent
+                            .spawn((compact_button(), button))
+                            .with_children(|parent| {
+                                parent.spawn(text(name));
+                            });
+                    }
+                });
+        });
+}
+
+/// Selects the building under a click, or drops the selected one there when
+/// it is being moved
+pub fn select_building(
+    mut commands: Commands,
+    mut clicks: EventReader<WorldClick>,
+    mut map_query: Query<&mut TileMap>,
+    mut selection: ResMut<SelectionState>,
+    actions: Res<ActionState>,
+    selected: Query<Entity, With<Selected>>,
+    mut buildings: Query<(&BuildingType, &mut GridPosition, &mut Transform), SelectableFilter>,
+) {
+    if actions.just_pressed(InputAction::Cancel) {
+        selection.moving = false;
+        for entity in selected.iter() {
+            commands.entity(entity).remove::<Selected>();
+        }
+    }
+    let Some(position) = clicks.read().last().map(|click| click.position) else {
+        return;
+    };
+    let Ok(mut map) = map_query.get_single_mut() else {
+        return;
+    };
+    let tile = (position.x >= 0.0 && position.y >= 0.0)
+        .then_some((position.x as usize, position.y as usize));
+
+    if selection.moving {
+        selection.moving = false;
+        let (Some((x, y)), Ok(entity)) = (tile, selected.get_single()) else {
+            return;
+        };
+        let Ok((building_type, mut grid, mut transform)) = buildings.get_mut(entity) else {
+            return;
+        };
+        if move_building(
+            &mut map,
+            entity,
+            *building_type,
+            &mut grid,
+            &mut transform,
+            x,
+            y,
+        ) {
+            info!("Moved {building_type:?} to ({x}, {y})");
+        }
+        return;
+    }
+
+    for entity in selected.iter() {
+        commands.entity(entity).remove::<Selected>();
+    }
+    if let Some(entity) = tile.and_then(|(x, y)| map.get_entity_at(x, y)) {
+        if buildings.contains(entity) {
+            commands.entity(entity).insert(Selected);
+        }
+    }
+}
+
+pub fn draw_selection(
+    mut gizmos: Gizmos,
+    selection: Res<SelectionState>,
+    view_mode: Res<ViewMode>,
+    query: Query<(&Transform, &GridSize), With<Selected>>,
+) {
+    for (transform, size) in query.iter() {
+        let center = transform.translation.truncate();
+        let size = Vec2::new(size.width as f32, size.height as f32);
+        let outline = |gizmos: &mut Gizmos, margin: f32, color: Color| {
+            let size = size + margin;
+            draw_footprint(gizmos, *view_mode, center - size / 2.0, size, color);
+        };
+        outline(&mut gizmos, 0.2, SELECTION_COLOR);
+        if selection.moving {
+            outline(&mut gizmos, 0.5, SELECTION_COLOR.with_alpha(0.4));
+        }
+    }
+}
+
+pub fn update_info_panel(
+    selection: Res<SelectionState>,
+    selected: Query<InfoTarget, (With<Selected>, SelectableFilter)>,
+    mut panel: Query<&mut Node, With<InfoPanelMarker>>,
+    mut text: Query<&mut Text, With<InfoTextMarker>>,
+) {
+    let Ok(mut node) = panel.get_single_mut() else {
+        return;
+    };
+    let Ok((building_type, building, collector, storage, defense, trap, factory, castle)) =
+        selected.get_single()
+    else {
+        node.display = Display::None;
+        return;
+    };
+    node.display = Display::Flex;
+
+    let mut content = building_type.name().to_string();
+    if let Some(building) = building {
+        content.push_str(&format!(
+            " (level {})\nHealth: {:.0}/{:.0}",
+            building.level, building.health, building.max_health
+        ));
+    }
+    if let Some(collector) = collector {
+        content.push_str(&format!(
+            "\nProduces {:.0} {:?}/s\nStored: {:.0}/{:.0}",
+            collector.production_rate,
+            collector.resource_type,
+            collector.stored,
+            collector.capacity
+        ));
+    }
+    if let Some(storage) = storage {
+        content.push_str(&format!(
+            "\nCapacity: {} {:?}",
+            storage.capacity, storage.resource_type
+        ));
+    }
+    if let Some(defense) = defense {
+        content.push_str(&format!(
+            "\nDamage: {:.0}\nRange: {:.1}",
+            defense.attack_damage, defense.attack_range
+        ));
+    }
+    if let Some(trap) = trap {
+        content.push_str(&format!(
+            "\nDamage: {:.0}\nTrigger radius: {:.1}",
+            trap.damage, trap.trigger_radius
+        ));
+    }
+    if let Some(factory) = factory {
+        content.push_str(&format!(
+            "\nBrewing: {}/{}",
+            factory.queue.len(),
+            factory.capacity
+        ));
+    }
+    if let Some(castle) = castle {
+        content.push_str(&format!(
+            "\nTroops: {}/{}",
+            castle.troops.len(),
+            castle.capacity
+        ));
+    }
+    let cost = building.map_or(0.0, |building| {
+        BUILDING_UPGRADE_COST[validate_level(building.level)]
+    });
+    if cost > 0.0 {
+        content.push_str(&format!("\nUpgrade: {cost:.0} gold"));
+    }
+    if selection.moving {
+        content.push_str("\nClick where to move it");
+    }
+
+    text.single_mut().0 = content;
+}
+
+/// Triggered bv [`handle_button_interactions`]
+pub fn info_button_handler(
+    mut commands: Commands,
+    mut events: EventReader<ButtonInteractionEvent<InfoButton>>,
+    mut resources: ResMut<PlayerResources>,
+    mut selection: ResMut<SelectionState>,
+    mut map_query: Query<&mut TileMap>,
+    mut selected: Query<SelectedBuilding, (With<Selected>, SelectableFilter)>,
+) {
+    for event in events.read() {
+        let ButtonInteractionEvent::Pressed(button) = event else {
+            continue;
+        };
+        let Ok((entity, building_type, building, position, collector)) = selected.get_single_mut()
+        else {
+            continue;
+        };
+        let Ok(mut map) = map_query.get_single_mut() else {
+            continue;
+        };
+
+        match button {
+            InfoButton::Upgrade => {
+                if matches!(building_type, BuildingType::HeroAltar(_)) {
+                    info!("Heroes are upgraded from their own button");
+                    continue;
+                }
+                let Some(building) = building else {
+                    info!("Traps don't level up");
+                    continue;
+                };
+                let cost = BUILDING_UPGRADE_COST[validate_level(building.level)];
+                let gold = resources.resources.entry(ResourceType::Gold).or_insert(0.0);
+                if cost <= 0.0 || *gold < cost {
+                    info!("Can't upgrade {building_type:?}, no gold or max level");
+                    continue;
+                }
+                if upgrade_building(&mut commands, entity, *building_type, building.level) {
+                    *gold -= cost;
+                    info!("Upgraded {building_type:?} to level {}", building.level + 1);
+                }
+            }
+            InfoButton::Collect => {
+                if let Some(mut collector) = collector {
+                    let amount = collector.collect(&mut resources);
+                    info!("Collected {amount:.0} {:?}", collector.resource_type);
+                }
+            }
+            InfoButton::Move => {
+                selection.moving = true;
+            }
+            InfoButton::Remove => {
+                if *building_type == BuildingType::TownHall {
+                    info!("The town hall can't be removed");
+                    continue;
+                }
+                remove_building(&mut commands, &mut map, entity, *building_type, position);
+                selection.moving = false;
+                info!("Removed {building_type:?}");
+            }
+        }
+    }
+}
+
+pub fn clear_selection(
+    mut commands: Commands,
+    selection: Option<ResMut<SelectionState>>,
+    selected: Query<Entity, With<Selected>>,
+    mut panel: Query<&mut Node, With<InfoPanelMarker>>,
+) {
+    for entity in selected.iter() {
+        commands.entity(entity).remove::<Selected>();
+    }
+    if let Some(mut selection) = selection {
+        selection.moving = false;
+    }
+    for mut node in panel.iter_mut() {
+        node.display = Display::None;
+    }
+}
@@ -0,0 +1,222 @@
+//! Settings screen, opened over the main menu or the pause menu
+use crate::prelude::*;
+use bevy::prelude::*;
+
+fn spawn_settings_button(parent: &mut ChildBuilder, label: impl Bundle, button: SettingsButton) {
+    parent
+        .spawn((
+            Button,
+            Node {
+                min_width: Val::Px(40.0),
+                padding: UiRect::axes(Val::Px(10.0), Val::Px(5.0)),
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            ColorPalette::new_with_bg(GRAY_35, GRAY_25, GRAY_15),
+            ThemeColor::Button,
+            button,
+        ))
+        .with_children(|parent| {
+            parent.spawn(label);
+        });
+}
+
+fn spawn_row(parent: &mut ChildBuilder, name: &str, content: impl FnOnce(&mut ChildBuilder)) {
+    parent
+        .spawn(Node {
+            flex_direction: FlexDirection::Row,
+            align_items: AlignItems::Center,
+            column_gap: Val::Px(10.0),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                Text(name.into()),
+                Node {
+                    width: Val::Px(180.0),
+                    ..default()
+                },
+            ));
+            content(parent);
+        });
+}
+
+/// Opens the settings screen unless it is already up
+pub fn open_settings(
+    commands: &mut Commands,
+    settings: &Settings,
+    open: &Query<Entity, With<SettingsMenuMarker>>,
+) {
+    if !open.is_empty() {
+        return;
+    }
+    let menu = spawn_modal(commands, 2, "Settings", |parent| {
+        for kind in SettingKind::STEPPED {
+            spawn_row(parent, kind.name(), |parent| {
+                spawn_settings_button(parent, text("-"), SettingsButton::Decrease(kind));
+                let (min, max, step) = Settings::range(kind);
+                let slider = Slider::new(settings.get(kind), min, max, step);
+                spawn_slider(parent, slider, SettingSlider(kind));
+                spawn_settings_button(parent, text("+"), SettingsButton::Increase(kind));
+                parent.spawn((
+                    Text::default(),
+                    Node {
+                        width: Val::Px(60.0),
+                        ..default()
+                    },
+                    SettingText(kind),
+                ));
+            });
+        }
+        for kind in SettingKind::TOGGLES {
+            spawn_row(parent, kind.name(), |parent| {
+                let on = settings.get(kind) > 0.5;
+                spawn_toggle(parent, on, SettingsButton::Toggle(kind));
+            });
+        }
+        parent.spawn(text("Controls"));
+        // Two columns, scrolled when the window is too short for all actions
+        parent
+            .spawn(scroll_list(Val::Vh(40.0)))
+            .with_children(|parent| {
+                parent
+                    .spawn(Node {
+                        display: Display::Grid,
+                        grid_template_columns: RepeatedGridTrack::auto(2),
+                        column_gap: Val::Px(20.0),
+                        row_gap: Val::Px(8.0),
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        for action in InputAction::ALL {
+                            spawn_row(parent, action.name(), |parent| {
+                                spawn_settings_button(
+                                    parent,
+                                    (Text::default(), BindingText(action)),
+                                    SettingsButton::Rebind(action),
+                                );
+                            });
+                        }
+                    });
+            });
+        parent
+            .spawn(Node {
+                flex_direction: FlexDirection::Row,
+                column_gap: Val::Px(10.0),
+                margin: UiRect::top(Val::Px(10.0)),
+                ..default()
+            })
+            .with_children(|parent| {
+                spawn_settings_button(parent, text("Defaults"), SettingsButton::Reset);
+                spawn_settings_button(parent, text("Close"), SettingsButton::Close);
+            });
+    });
+    commands.entity(menu).insert(SettingsMenuMarker);
+}
+
+pub fn close_settings(
+    mut commands: Commands,
+    mut rebinding: ResMut<Rebinding>,
+    query: Query<Entity, With<SettingsMenuMarker>>,
+) {
+    rebinding.0 = None;
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Triggered bv [`handle_button_interactions`]
+pub fn settings_button_handler(
+    mut commands: Commands,
+    mut events: EventReader<ButtonInteractionEvent<SettingsButton>>,
+    mut settings: ResMut<Settings>,
+    mut rebinding: ResMut<Rebinding>,
+    query: Query<Entity, With<SettingsMenuMarker>>,
+) {
+    for event in events.read() {
+        if let ButtonInteractionEvent::Pressed(button) = event {
+            match button {
+                SettingsButton::Decrease(kind) => settings.step(*kind, false),
+                SettingsButton::Increase(kind) => settings.step(*kind, true),
+                SettingsButton::Toggle(kind) => settings.toggle(*kind),
+                SettingsButton::Rebind(action) => rebinding.0 = Some(*action),
+                SettingsButton::Reset => *settings = Settings::default(),
+                SettingsButton::Close => {
+                    rebinding.0 = None;
+                    for entity in query.iter() {
+                        commands.entity(entity).despawn_recursive();
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Binds the next key pressed to the action waiting for one
+pub fn capture_rebinding(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut rebinding: ResMut<Rebinding>,
+    mut settings: ResMut<Settings>,
+) {
+    let Some(action) = rebinding.0 else {
+        return;
+    };
+    if let Some(key) = keyboard.get_just_pressed().next() {
+        settings.bindings.rebind_key(action, *key);
+        rebinding.0 = None;
+        info!("Bound {action:?} to {key:?}");
+    }
+}
+
+/// Sets the settings from their sliders as they get dragged
+pub fn apply_setting_sliders(
+    mut settings: ResMut<Settings>,
+    sliders: Query<(&Slider, &SettingSlider), Changed<Slider>>,
+) {
+    for (slider, SettingSlider(kind)) in sliders.iter() {
+        if settings.get(*kind) != slider.value {
+            settings.set(*kind, slider.value);
+        }
+    }
+}
+
+type SettingWidgets<'a> = (
+    Option<&'a SettingSlider>,
+    Option<&'a mut Slider>,
+    Option<&'a SettingsButton>,
+    Option<&'a mut Toggle>,
+);
+
+pub fn update_settings_menu(
+    settings: Res<Settings>,
+    rebinding: Res<Rebinding>,
+    mut values: Query<(&SettingText, &mut Text), Without<BindingText>>,
+    mut bindings: Query<(&BindingText, &mut Text), Without<SettingText>>,
+    mut widgets: Query<SettingWidgets>,
+) {
+    for (SettingText(kind), mut text) in values.iter_mut() {
+        text.0 = settings.display(*kind);
+    }
+    for (BindingText(action), mut text) in bindings.iter_mut() {
+        text.0 = if rebinding.0 == Some(*action) {
+            "Press a key...".into()
+        } else {
+            settings.bindings.label(*action)
+        };
+    }
+    // Only written when they differ, so the sliders don't report a change
+    for (setting, slider, button, toggle) in widgets.iter_mut() {
+        if let (Some(SettingSlider(kind)), Some(mut slider)) = (setting, slider) {
+            let value = settings.get(*kind);
+            if slider.value != value {
+                slider.value = value;
+            }
+        }
+        if let (Some(SettingsButton::Toggle(kind)), Some(mut toggle)) = (button, toggle) {
+            let on = settings.get(*kind) > 0.5;
+            if toggle.on != on {
+                toggle.on = on;
+            }
+        }
+    }
+}
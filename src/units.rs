@@ -0,0 +1,141 @@
+//! Here are the unit constructors
+use crate::prelude::*;
+use bevy::prelude::*;
+
+fn unit_transform(position: Vec2) -> Transform {
+    Transform::from_translation(position.extend(UNIT_Z))
+}
+
+pub fn barbarian(
+    commands: &mut Commands,
+    assets: &UnitAssets,
+    level: u32,
+    position: Vec2,
+) -> Entity {
+    let idx = validate_level(level);
+    let health = BARBARIAN_HEALTH[idx];
+
+    commands
+        .spawn((
+            Unit {
+                health,
+                max_health: health,
+                attack_damage: BARBARIAN_DAMAGE[idx],
+                attack_range: BARBARIAN_RANGE,
+                attack_speed: BARBARIAN_SPEED,
+                movement_speed: BARBARIAN_MOVEMENT,
+                target_preference: TargetPreference::AnyBuilding,
+                layer: UnitLayer::Ground,
+            },
+            UnitType::Barbarian,
+            unit_transform(position),
+            assets.get(&UnitType::Barbarian).to_component(),
+        ))
+        .id()
+}
+
+pub fn wall_breaker(
+    commands: &mut Commands,
+    assets: &UnitAssets,
+    level: u32,
+    position: Vec2,
+) -> Entity {
+    let idx = validate_level(level);
+    let health = WALL_BREAKER_HEALTH[idx];
+
+    commands
+        .spawn((
+            Unit {
+                health,
+                max_health: health,
+                attack_damage: WALL_BREAKER_DAMAGE[idx],
+                attack_range: WALL_BREAKER_RANGE,
+                attack_speed: WALL_BREAKER_SPEED,
+                movement_speed: WALL_BREAKER_MOVEMENT,
+                target_preference: TargetPreference::AnyBuilding,
+                layer: UnitLayer::Ground,
+            },
+            WallBreaker::new(WALL_BREAKER_MULTIPLIER),
+            UnitType::WallBreaker,
+            unit_transform(position),
+            assets.get(&UnitType::WallBreaker).to_component(),
+        ))
+        .id()
+}
+
+pub fn minion(commands: &mut Commands, assets: &UnitAssets, level: u32, position: Vec2) -> Entity {
+    let idx = validate_level(level);
+    let health = MINION_HEALTH[idx];
+
+    commands
+        .spawn((
+            Unit {
+                health,
+                max_health: health,
+                attack_damage: MINION_DAMAGE[idx],
+                attack_range: MINION_RANGE,
+                attack_speed: MINION_SPEED,
+                movement_speed: MINION_MOVEMENT,
+                target_preference: TargetPreference::AnyBuilding,
+                layer: UnitLayer::Air,
+            },
+            UnitType::Minion,
+            unit_transform(position),
+            assets.get(&UnitType::Minion).to_component(),
+        ))
+        .id()
+}
+
+pub fn hero(
+    commands: &mut Commands,
+    assets: &UnitAssets,
+    hero_type: HeroType,
+    level: u32,
+    health: f32,
+    position: Vec2,
+    altar: Option<Entity>,
+) -> Entity {
+    let idx = validate_level(level);
+    let (attack_damage, attack_range, movement_speed) = match hero_type {
+        HeroType::King => (KING_DAMAGE[idx], KING_RANGE, KING_MOVEMENT),
+        HeroType::Queen => (QUEEN_DAMAGE[idx], QUEEN_RANGE, QUEEN_MOVEMENT),
+    };
+
+    commands
+        .spawn((
+            Unit {
+                health,
+                max_health: hero_type.max_health(level),
+                attack_damage,
+                attack_range,
+                attack_speed: HERO_ATTACK_SPEED,
+                movement_speed,
+                target_preference: TargetPreference::AnyBuilding,
+                layer: UnitLayer::Ground,
+            },
+            Hero::new(hero_type, altar),
+            UnitType::Hero(hero_type),
+            unit_transform(position),
+            assets.get(&UnitType::Hero(hero_type)).to_component(),
+        ))
+        .id()
+}
+
+// Function for deploying any unit
+pub fn deploy_unit(
+    commands: &mut Commands,
+    assets: &UnitAssets,
+    unit_type: UnitType,
+    level: u32,
+    position: Vec2,
+) -> Entity {
+    match unit_type {
+        UnitType::Barbarian => barbarian(commands, assets, level, position),
+        UnitType::WallBreaker => wall_breaker(commands, assets, level, position),
+        UnitType::Minion => minion(commands, assets, level, position),
+        UnitType::Hero(hero_type) => {
+            let health = hero_type.max_health(level);
+            hero(commands, assets, hero_type, level, health, position, None)
+        }
+    }
+}